@@ -0,0 +1,138 @@
+//! Long-running keeper executing every open FX swap once its conditions are met
+use crate::output::Output;
+use crate::Keeper;
+use log::{debug, info, warn};
+use m10_fx_solana::state::FxData;
+use m10_fx_solana_client::{FxClient, FxClientError, FxClientResult, SwapFilter};
+use serde_json::json;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+use std::sync::Mutex;
+use std::thread::{scope, sleep};
+use std::time::Duration;
+
+/// Scan the open swaps every [`Keeper::interval`], executing the eligible ones until interrupted
+pub fn run(fx: &FxClient, payer: &Keypair, keeper: &Keeper, output: Output) {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let interval = Duration::from_secs(keeper.interval);
+    loop {
+        if let Err(err) = scan(fx, payer, keeper, output) {
+            warn!("event=scan_failed error={:?}", err.to_string());
+        }
+        sleep(interval);
+    }
+}
+
+/// Execute every open swap whose conditions are met at the current rate
+fn scan(fx: &FxClient, payer: &Keypair, keeper: &Keeper, output: Output) -> FxClientResult<()> {
+    let swaps = fx.list_swaps(&SwapFilter::default())?;
+    let now = fx.cluster_time()?;
+    let eligible: Vec<_> = swaps
+        .iter()
+        .filter(|(fx_account, fx_data)| match fx.swap_rate(fx_data) {
+            Ok(quote) => {
+                debug!(
+                    "event=evaluated fx_account={} rate={} lower={} upper={} valid_until={}",
+                    fx_account,
+                    quote.rate,
+                    fx_data.limits.start,
+                    fx_data.limits.end,
+                    fx_data.valid_until
+                );
+                // Only submit the executions bound to succeed
+                fx_data.check_execution(&quote, now).is_ok()
+            }
+            Err(FxClientError::OnChainRate(oracle, fx_feed)) => {
+                // Leave evaluating the rate to the program, which rejects unmet conditions
+                debug!(
+                    "event=unevaluated fx_account={} oracle={:?} fx_feed={}",
+                    fx_account, oracle, fx_feed
+                );
+                true
+            }
+            Err(err) => {
+                warn!(
+                    "event=rate_failed fx_account={} error={:?}",
+                    fx_account,
+                    err.to_string()
+                );
+                false
+            }
+        })
+        .collect();
+    info!(
+        "event=scanned open={} eligible={}",
+        swaps.len(),
+        eligible.len()
+    );
+
+    // Workers take the next eligible swap until none are left
+    let queue = Mutex::new(eligible.into_iter());
+    scope(|workers| {
+        for _ in 0..keeper.concurrency.max(1) {
+            workers.spawn(|| loop {
+                let next = queue.lock().expect("Poisoned queue").next();
+                match next {
+                    Some((fx_account, fx_data)) => {
+                        execute(fx, payer, keeper, output, fx_account, fx_data)
+                    }
+                    None => break,
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Execute the swap through its provider's delegate, retrying failed submissions with an exponential backoff
+fn execute(
+    fx: &FxClient,
+    payer: &Keypair,
+    keeper: &Keeper,
+    output: Output,
+    fx_account: &Pubkey,
+    fx_data: &FxData,
+) {
+    let mut backoff = Duration::from_secs(keeper.backoff);
+    for attempt in 0..=keeper.retries {
+        match fx.execute_swap(fx_account, fx_data, payer, None, None) {
+            Ok(signature) => {
+                info!(
+                    "event=executed fx_account={} signature={} attempt={}",
+                    fx_account, signature, attempt
+                );
+                output.result(json!({
+                    "signature": signature.to_string(),
+                    "fx_account": fx_account.to_string(),
+                    "amount": fx_data.amount_remaining(),
+                }));
+                return;
+            }
+            Err(err) if err.is_rejected() => {
+                // The program refused the execution, resubmitting won't help
+                warn!(
+                    "event=rejected fx_account={} error={:?}",
+                    fx_account,
+                    err.to_string()
+                );
+                return;
+            }
+            Err(err) => {
+                warn!(
+                    "event=failed fx_account={} attempt={} error={:?}",
+                    fx_account,
+                    attempt,
+                    err.to_string()
+                );
+                if attempt < keeper.retries {
+                    sleep(backoff);
+                    backoff *= 2;
+                }
+            }
+        }
+    }
+    warn!(
+        "event=abandoned fx_account={} retries={}",
+        fx_account, keeper.retries
+    );
+}
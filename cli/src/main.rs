@@ -1,31 +1,31 @@
+mod keeper;
+mod output;
+
+use crate::output::{report, Context, Output};
 use clap::Parser;
-use m10_fx_solana::liquidity::{DemoLiquidity, LiquidityProvider};
-use m10_fx_solana::rates::{feed_for_token, DemoFx, FxRates};
-use m10_fx_solana::state::FxData;
-use m10_fx_solana::utils::pda_swap;
+use m10_fx_solana::error::FxError;
+use m10_fx_solana::rates::Oracle;
+use m10_fx_solana::state::{ExpiryPolicy, FxData, FxFeed, SwapKind};
+use m10_fx_solana::utils::{pda_liquidity_authority, pda_receipt};
+use m10_fx_solana_client::{FxClient, FxClientError, SwapFilter, SwapRequest};
 use rust_decimal::prelude::One;
 use rust_decimal::Decimal;
-use solana_client::client_error::ClientError;
+use serde_json::{json, Value};
 use solana_client::rpc_client::RpcClient;
-use solana_program::account_info::AccountInfo;
-use solana_program::instruction::InstructionError;
-use solana_program::program_pack::Pack;
+use solana_program::instruction::Instruction;
 use solana_program::pubkey::Pubkey;
-use solana_program::system_instruction::create_account;
-use solana_sdk::account::ReadableAccount;
-use solana_sdk::commitment_config::CommitmentConfig;
-use solana_sdk::signature::{read_keypair_file, Keypair};
+use solana_sdk::signature::{read_keypair_file, Keypair, Signature};
 use solana_sdk::signer::Signer;
-use solana_sdk::transaction::{Transaction, TransactionError};
-use spl_token::state::{Account, Mint};
-use std::cell::RefCell;
-use std::path::PathBuf;
-use std::rc::Rc;
+use std::path::{Path, PathBuf};
+use std::process::exit;
 use std::thread::sleep;
 use std::time::Duration;
 
 const DEFAULT_RPC_URL: &str = "http://127.0.0.1:8899";
 const EXECUTE_INTERVAL: Duration = Duration::from_secs(15);
+const DEFAULT_MAX_RATE_AGE: u64 = 3600;
+/// Lamports rewarding whoever executes a swap, covering a couple of transaction fees
+const DEFAULT_CRANK_REWARD: u64 = 10_000;
 
 #[derive(Parser)]
 #[clap(name = "command")]
@@ -33,14 +33,36 @@ const EXECUTE_INTERVAL: Duration = Duration::from_secs(15);
 struct Command {
     #[clap(short, long)]
     url: Option<String>,
+    #[clap(
+        long,
+        value_enum,
+        default_value = "text",
+        global = true,
+        help = "Report results as text or as JSON objects"
+    )]
+    output: Output,
     #[clap(subcommand)]
-    command: RPC,
+    command: Rpc,
 }
 
 #[derive(clap::Subcommand, Debug)]
-enum RPC {
+enum Rpc {
     Initiate(Initiate),
     Execute(Execute),
+    Cancel(Cancel),
+    RegisterLiquidity(Liquidity),
+    DeregisterLiquidity(Liquidity),
+    AddFeed(Feed),
+    UpdateFeed(Feed),
+    RemoveFeed(RemoveFeed),
+    SetBaseMint(SetBaseMint),
+    Migrate(Migrate),
+    Refund(Refund),
+    SetFees(SetFees),
+    Keeper(Keeper),
+    Show(Show),
+    List(List),
+    Receipt(Receipt),
 }
 
 #[derive(clap::Args, Debug)]
@@ -64,6 +86,37 @@ struct Initiate {
     margin: Decimal,
     #[clap(short, long, value_parser, help = "Duration in seconds")]
     valid_for: Option<u64>,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        default_value = "oco",
+        help = "Limits triggering the swap: limit (upper), stop (lower) or oco (either)"
+    )]
+    kind: SwapKind,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        default_value = "settle",
+        help = "On expiry, settle at the market rate or refund: settle or refund"
+    )]
+    expiry: ExpiryPolicy,
+    #[clap(
+        long,
+        value_parser,
+        help = "Nonce deriving the FX account, defaults to the current time in nanoseconds. Never reuse a nonce"
+    )]
+    nonce: Option<u64>,
+    #[clap(
+        long,
+        value_parser,
+        default_value_t = DEFAULT_CRANK_REWARD,
+        help = "Lamports rewarding whoever executes the swap"
+    )]
+    crank_reward: u64,
+    #[clap(long, help = "Record the swap's executions in a settlement receipt")]
+    receipt: bool,
 }
 
 #[derive(clap::Args, Debug)]
@@ -71,243 +124,874 @@ struct Initiate {
 struct Execute {
     #[clap(short, long, value_parser)]
     fx_account: Pubkey,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Liquidity provider key, omit to execute through the provider's delegate & earn the crank reward"
+    )]
+    liquidity: Option<PathBuf>,
     #[clap(short, long, value_parser)]
+    payer: PathBuf,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Maximum amount to fill, in the initiated token, defaults to the remaining amount. Requires the liquidity key"
+    )]
+    max_amount: Option<u64>,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Cancel {
+    #[clap(short, long, value_parser)]
+    fx_account: Pubkey,
+    #[clap(short, long)]
+    signer: PathBuf,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Token account receiving the refund, defaults to the refund account of the swap"
+    )]
+    refund: Option<Pubkey>,
+    #[clap(short, long, value_parser)]
+    payer: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Liquidity {
+    #[clap(short, long, help = "Administrator of the liquidity registry")]
+    admin: PathBuf,
+    #[clap(short, long, value_parser, help = "Token account providing liquidity")]
+    liquidity: Pubkey,
+    #[clap(short, long, value_parser)]
+    payer: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Feed {
+    #[clap(short, long, help = "Administrator of the FX feed registry")]
+    admin: PathBuf,
+    #[clap(long, value_parser)]
+    from_mint: Pubkey,
+    #[clap(long, value_parser)]
+    to_mint: Pubkey,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Account providing the exchange rate"
+    )]
+    feed: Pubkey,
+    #[clap(short, long, value_parser, help = "Oracle program owning the feed")]
+    oracle_program: Pubkey,
+    #[clap(
+        long,
+        value_parser,
+        default_value = "demo",
+        help = "Oracle reading the feed: demo, chainlink or pyth"
+    )]
+    oracle: Oracle,
+    #[clap(long, help = "The feed quotes the inverse of the currency pair")]
+    inverted: bool,
+    #[clap(
+        long,
+        value_parser,
+        default_value_t = DEFAULT_MAX_RATE_AGE,
+        help = "Maximum age of a quoted rate in seconds"
+    )]
+    max_age: u64,
+    #[clap(short, long, value_parser)]
+    payer: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct RemoveFeed {
+    #[clap(short, long, help = "Administrator of the FX feed registry")]
+    admin: PathBuf,
+    #[clap(long, value_parser)]
+    from_mint: Pubkey,
+    #[clap(long, value_parser)]
+    to_mint: Pubkey,
+    #[clap(short, long, value_parser)]
+    payer: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct SetBaseMint {
+    #[clap(short, long, help = "Administrator of the FX feed registry")]
+    admin: PathBuf,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Currency to derive cross rates through, omit to disable cross rates"
+    )]
+    base_mint: Option<Pubkey>,
+    #[clap(short, long, value_parser)]
+    payer: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Migrate {
+    #[clap(short, long, value_parser)]
+    fx_account: Pubkey,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Key of the liquidity provider paid when the swap was initiated, escrowing its amount again"
+    )]
     liquidity: PathBuf,
     #[clap(short, long, value_parser)]
     payer: PathBuf,
 }
 
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct SetFees {
+    #[clap(short, long, help = "Administrator of the fee configuration")]
+    admin: PathBuf,
+    #[clap(long, value_parser, help = "Protocol fee in basis points")]
+    fee_bps: u16,
+    #[clap(long, value_parser, help = "Liquidity provider spread in basis points")]
+    spread_bps: u16,
+    #[clap(
+        long,
+        value_parser,
+        help = "Owner of the token accounts collecting the protocol fee"
+    )]
+    fee_collector: Pubkey,
+    #[clap(short, long, value_parser)]
+    payer: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Refund {
+    #[clap(short, long, value_parser)]
+    fx_account: Pubkey,
+    #[clap(short, long, value_parser)]
+    payer: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Keeper {
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Pays for the executions & receives the crank rewards"
+    )]
+    payer: PathBuf,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        default_value_t = EXECUTE_INTERVAL.as_secs(),
+        help = "Seconds between scans of the open swaps"
+    )]
+    interval: u64,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        default_value_t = 4,
+        help = "Maximum number of swaps executed at once"
+    )]
+    concurrency: usize,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        default_value_t = 3,
+        help = "Attempts to resubmit a failed execution"
+    )]
+    retries: u32,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        default_value_t = 2,
+        help = "Seconds before the first retry, doubling on every next one"
+    )]
+    backoff: u64,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Show {
+    #[clap(value_parser)]
+    fx_account: Pubkey,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Receipt {
+    #[clap(
+        value_parser,
+        help = "FX account of the swap, which may already be closed"
+    )]
+    fx_account: Pubkey,
+}
+
+#[derive(clap::Args, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct List {
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Only list the swaps of this initializer"
+    )]
+    initializer: Option<Pubkey>,
+    #[clap(
+        short,
+        long,
+        value_parser,
+        help = "Only list the swaps towards this account"
+    )]
+    to: Option<Pubkey>,
+}
+
 pub fn main() {
-    let Command { url, command } = Command::parse();
+    let Command {
+        url,
+        output,
+        command,
+    } = Command::parse();
+
+    let fx = FxClient::new(RpcClient::new(
+        url.unwrap_or_else(|| DEFAULT_RPC_URL.to_string()),
+    ));
 
-    let client = RpcClient::new(url.unwrap_or_else(|| DEFAULT_RPC_URL.to_string()));
+    if let Err(err) = run(&fx, output, command) {
+        output.error(&err);
+        exit(1);
+    }
+}
 
+/// Run the [`command`], describing what failed otherwise
+fn run(fx: &FxClient, output: Output, command: Rpc) -> Result<(), String> {
     match command {
-        RPC::Initiate(initiate) => {
-            println!("{:?}", initiate);
-
-            let signer = read_keypair_file(&initiate.signer).expect("Invalid key pair");
-            let payer = read_keypair_file(&initiate.payer).expect("Could not read payer key");
-            let mut instructions = vec![];
-
-            let account = client
-                .get_account(&initiate.from)
-                .expect("Could not retrieve account");
-            let account_data = Account::unpack(&account.data).expect("invalid account data");
-            let mint_account = client
-                .get_account(&account_data.mint)
-                .expect("Could not find mint");
-            let mint_data = Mint::unpack(&mint_account.data).expect("invalid mint account data");
-            let to_account = client
-                .get_account(&initiate.to)
-                .expect("could not retrieve account");
-            let to_account_data = Account::unpack(&to_account.data).expect("invalid account data");
-            let from_liquidity =
-                DemoLiquidity::liquidity_account(&account_data).expect("No liquidity provider");
-            let fx_feed =
-                feed_for_token(&account_data.mint, &to_account_data.mint).expect("unknown fx feed");
-
-            // Keys
-            let new_key = Keypair::new();
-            let fx_key = Keypair::new();
-            // Generate PDA
-            let (pda, _bump_seed) = pda_swap();
-
-            // Create an empty account
-            let lamports = client
-                .get_minimum_balance_for_rent_exemption(Account::LEN)
-                .expect("Could not get rent-exempt balance");
-            let create_account_ix = create_account(
-                &payer.pubkey(),
-                &new_key.pubkey(),
-                lamports,
-                Account::LEN as u64,
-                &spl_token::id(),
-            );
-            instructions.push(create_account_ix);
-
-            // Initialize SPL token holding account
-            let init_account_ix = spl_token::instruction::initialize_account(
-                &spl_token::id(),
-                &new_key.pubkey(),
-                &account_data.mint,
-                &pda,
-            )
-            .expect("could not create init account instruction");
-            instructions.push(init_account_ix);
-
-            // Transfer some tokens to holding account
-            let transfer_to_holding = spl_token::instruction::transfer(
-                &spl_token::id(),
-                &initiate.from,
-                &new_key.pubkey(),
-                &initiate.from,
-                &[],
-                initiate.amount,
-            )
-            .expect("Could not create transfer instruction");
-            instructions.push(transfer_to_holding);
-
-            // Create the FX account
-            let lamports = client
-                .get_minimum_balance_for_rent_exemption(FxData::LEN)
-                .expect("Could not get rent-exempt balance");
-            let create_account_ix = create_account(
-                &payer.pubkey(),
-                &fx_key.pubkey(),
-                lamports,
-                FxData::LEN as u64,
-                &m10_fx_solana::id(),
-            );
-            instructions.push(create_account_ix);
+        Rpc::Initiate(initiate) => {
+            report!(output, "{:?}", initiate);
+
+            let signer = read_keypair_file(&initiate.signer).context("Invalid key pair")?;
+            let payer = read_keypair_file(&initiate.payer).context("Could not read payer key")?;
+            let from_mint = fx
+                .token_account(&initiate.from)
+                .context("Could not retrieve account")?
+                .mint;
+            let to_mint = fx
+                .token_account(&initiate.to)
+                .context("Could not retrieve account")?
+                .mint;
 
             // Define limits
             if initiate.margin.is_sign_negative() || initiate.margin > Decimal::one() {
-                panic!("Margin should be between 0.0 & 1.0: {}", initiate.margin);
+                return Err(format!(
+                    "Margin should be between 0.0 & 1.0: {}",
+                    initiate.margin
+                ));
             }
-            let mut fake_1 = FakeAccounts::default();
-            let mut fake_2 = FakeAccounts::default();
-            let rate = DemoFx::rate(&fake_1.info(&from_liquidity), &fake_2.info(&fx_feed))
-                .expect("Could not get current FX rate");
-            println!("Current exchange rate {}", rate);
+            let quote = fx
+                .quote(&from_mint, &to_mint, initiate.amount)
+                .context("Could not get current FX rate")?;
+            let rate = quote.rate.rate;
+            report!(output, "Current exchange rate {}", rate);
+            report!(
+                output,
+                "Quoted amount {}",
+                spl_token::amount_to_ui_amount(quote.quoted, quote.to_decimals)
+            );
+            report!(
+                output,
+                "Net amount {} after a fee of {} & a spread of {}",
+                spl_token::amount_to_ui_amount(quote.charges.net, quote.to_decimals),
+                spl_token::amount_to_ui_amount(quote.charges.fee, quote.to_decimals),
+                spl_token::amount_to_ui_amount(quote.charges.spread, quote.to_decimals)
+            );
             let min = rate * (Decimal::one() - initiate.margin);
             let max = rate * (Decimal::one() + initiate.margin);
-            println!(
+            report!(
+                output,
                 "Set margin to {}. Limits: [{}, {}]",
-                initiate.margin, min, max
-            );
-
-            // Invoke the Initiate command
-            let initiate_ix = m10_fx_solana::instruction::initiate(
-                initiate.from,
-                new_key.pubkey(),
-                initiate.to,
-                fx_key.pubkey(),
-                fx_feed,
-                from_liquidity,
-                initiate.amount,
-                max,
+                initiate.margin,
                 min,
-                initiate.valid_for.map(Duration::from_secs),
-            );
-            instructions.push(initiate_ix);
-
-            // get a blockhash
-            let recent_blockhash = client
-                .get_latest_blockhash()
-                .expect("error: unable to get recent blockhash");
-
-            // Execute transactions
-            let tx = Transaction::new_signed_with_payer(
-                &instructions,
-                Some(&payer.pubkey()),
-                &[&payer, &new_key, &signer, &fx_key],
-                recent_blockhash,
+                max
             );
-            if let Err(err) = client.send_and_confirm_transaction_with_spinner(&tx) {
-                panic!("{:#?}", err);
-            }
-            println!(
+
+            let request = SwapRequest {
+                from: initiate.from,
+                to: initiate.to,
+                refund_account: initiate.from,
+                amount: initiate.amount,
+                limits: min..max,
+                valid_for: initiate.valid_for.map(Duration::from_secs),
+                kind: initiate.kind,
+                expiry: initiate.expiry,
+                nonce: initiate.nonce,
+                crank_reward: initiate.crank_reward,
+                receipt: initiate.receipt,
+            };
+            let swap = fx
+                .initiate_swap(&request, &signer, &payer)
+                .context("Could not initiate the FX swap")?;
+            report!(
+                output,
                 "Created account {} with {} funds",
-                new_key.pubkey(),
-                spl_token::amount_to_ui_amount(initiate.amount, mint_data.decimals)
+                swap.holding,
+                spl_token::amount_to_ui_amount(initiate.amount, quote.from_decimals)
             );
-            println!("Created FX account {}", fx_key.pubkey());
+            report!(
+                output,
+                "Created FX account {} with nonce {}",
+                swap.fx_account,
+                swap.nonce
+            );
+            output.result(json!({
+                "signature": swap.signature.to_string(),
+                "fx_account": swap.fx_account.to_string(),
+                "nonce": swap.nonce,
+                "holding": swap.holding.to_string(),
+                "vault": swap.vault.to_string(),
+                "amount": initiate.amount,
+                "rate": rate.to_string(),
+                "quoted": quote.quoted,
+                "net": quote.charges.net,
+                "fee": quote.charges.fee,
+                "spread": quote.charges.spread,
+                "limits": {"lower": min.to_string(), "upper": max.to_string()},
+            }));
         }
-        RPC::Execute(execute) => {
-            println!("{:?}", execute);
-            let payer = read_keypair_file(&execute.payer).expect("Could not find payer key");
+        Rpc::Execute(execute) => {
+            report!(output, "{:?}", execute);
+            let payer = read_keypair_file(&execute.payer).context("Could not find payer key")?;
+            // The liquidity provider selected on initiate fills the swap,
+            // either signing itself or paying out through its delegate
+            let liquidity = execute
+                .liquidity
+                .as_ref()
+                .map(read_keypair_file)
+                .transpose()
+                .context("Could not read liquidity key")?;
             loop {
-                match try_execute(&client, &payer, &execute) {
-                    Ok(_) => {
-                        println!("Successfully executed FX swap");
-                        return;
+                let fx_data = fx
+                    .fetch_swap(&execute.fx_account)
+                    .context("Could not retrieve FX account")?;
+
+                // Only submit the execution once it would succeed,
+                // unless the rate can only be evaluated by the program
+                let quote = match fx.swap_rate(&fx_data) {
+                    Ok(quote) => Some(quote),
+                    Err(err @ FxClientError::OnChainRate(..)) => {
+                        report!(output, "{}, submitting the execution", err);
+                        None
                     }
-                    Err(err) => {
-                        if let Some(TransactionError::InstructionError(
-                            _,
-                            InstructionError::Custom(7),
-                        )) = err.get_transaction_error()
-                        {
-                            // Swap conditions not met
-                            println!("Swap conditions not met. Sleeping {:?}", EXECUTE_INTERVAL);
+                    Err(err) => return Err(err).context("Could not get current FX rate"),
+                };
+                if let Some(quote) = &quote {
+                    let now = fx.cluster_time().context("Could not get cluster time")?;
+                    match fx_data.check_execution(quote, now) {
+                        Ok(_) => {}
+                        Err(FxError::SwapExpired) => {
+                            report!(
+                                output,
+                                "FX swap {} expired, use `refund` to return the funds",
+                                execute.fx_account
+                            );
+                            output.result(json!({
+                                "fx_account": execute.fx_account.to_string(),
+                                "expired": true,
+                            }));
+                            return Ok(());
+                        }
+                        Err(err) => {
+                            report!(
+                                output,
+                                "{} at {}. Sleeping {:?}",
+                                err,
+                                quote.rate,
+                                EXECUTE_INTERVAL
+                            );
                             sleep(EXECUTE_INTERVAL);
-                        } else {
-                            panic!("{:#?}", err);
+                            continue;
                         }
                     }
                 }
+
+                match fx.execute_swap(
+                    &execute.fx_account,
+                    &fx_data,
+                    &payer,
+                    liquidity.as_ref().map(|liquidity| liquidity as &dyn Signer),
+                    execute.max_amount,
+                ) {
+                    Ok(signature) => {
+                        let amount_filled = match fx.fetch_swap(&execute.fx_account).ok() {
+                            Some(fx_data) => {
+                                report!(
+                                    output,
+                                    "Partially filled FX swap: {} of {}",
+                                    fx_data.amount_filled,
+                                    fx_data.amount
+                                );
+                                fx_data.amount_filled
+                            }
+                            None => {
+                                report!(output, "Successfully executed FX swap");
+                                fx_data.amount
+                            }
+                        };
+                        output.result(json!({
+                            "signature": signature.to_string(),
+                            "fx_account": execute.fx_account.to_string(),
+                            "rate": quote.map(|quote| quote.rate.to_string()),
+                            "amount": fx_data.amount,
+                            "amount_filled": amount_filled,
+                            "completed": amount_filled == fx_data.amount,
+                        }));
+                        return Ok(());
+                    }
+                    Err(FxClientError::Fx(FxError::SwapConditionsNotMet)) => {
+                        // The rate moved before the swap executed
+                        report!(
+                            output,
+                            "Swap conditions not met. Sleeping {:?}",
+                            EXECUTE_INTERVAL
+                        );
+                        sleep(EXECUTE_INTERVAL);
+                    }
+                    Err(err) => return Err(err).context("Could not execute the FX swap"),
+                }
             }
         }
-    }
+        Rpc::Cancel(cancel) => {
+            report!(output, "{:?}", cancel);
+            let signer = read_keypair_file(&cancel.signer).context("Invalid key pair")?;
+            let payer = read_keypair_file(&cancel.payer).context("Could not read payer key")?;
+
+            let fx_data = fx
+                .fetch_swap(&cancel.fx_account)
+                .context("Could not retrieve FX account")?;
+            if fx_data.initializer != signer.pubkey() {
+                return Err(format!(
+                    "Mismatched initializer, expected {}",
+                    fx_data.initializer
+                ));
+            }
+
+            let refund_account = cancel.refund.unwrap_or(fx_data.refund_account);
+            let cancel_ix = m10_fx_solana::instruction::cancel(
+                fx_data.initializer,
+                refund_account,
+                cancel.fx_account,
+            );
+
+            let signature = fx
+                .send(&[cancel_ix], &payer, &[&signer])
+                .context("Could not cancel the FX swap")?;
+            report!(output, "Cancelled FX swap {}", cancel.fx_account);
+            output.result(json!({
+                "signature": signature.to_string(),
+                "fx_account": cancel.fx_account.to_string(),
+                "refund_account": refund_account.to_string(),
+                "amount": fx_data.amount_remaining(),
+            }));
+        }
+        Rpc::RegisterLiquidity(liquidity) => {
+            report!(output, "{:?}", liquidity);
+            let admin = read_keypair_file(&liquidity.admin).context("Invalid admin key pair")?;
+            let ix =
+                m10_fx_solana::instruction::register_liquidity(admin.pubkey(), liquidity.liquidity);
+            let signature = update_registry(fx, &admin, &liquidity.payer, ix)?;
+            report!(
+                output,
+                "Registered liquidity provider {}",
+                liquidity.liquidity
+            );
+            report!(
+                output,
+                "Approve {} as its delegate to let anyone execute swaps",
+                pda_liquidity_authority(&liquidity.liquidity).0
+            );
+            output.result(json!({
+                "signature": signature.to_string(),
+                "liquidity": liquidity.liquidity.to_string(),
+                "liquidity_authority": pda_liquidity_authority(&liquidity.liquidity).0.to_string(),
+            }));
+        }
+        Rpc::DeregisterLiquidity(liquidity) => {
+            report!(output, "{:?}", liquidity);
+            let admin = read_keypair_file(&liquidity.admin).context("Invalid admin key pair")?;
+            let ix = m10_fx_solana::instruction::deregister_liquidity(
+                admin.pubkey(),
+                liquidity.liquidity,
+            );
+            let signature = update_registry(fx, &admin, &liquidity.payer, ix)?;
+            report!(
+                output,
+                "Deregistered liquidity provider {}",
+                liquidity.liquidity
+            );
+            output.result(json!({
+                "signature": signature.to_string(),
+                "liquidity": liquidity.liquidity.to_string(),
+            }));
+        }
+        Rpc::AddFeed(feed) => {
+            report!(output, "{:?}", feed);
+            let admin = read_keypair_file(&feed.admin).context("Invalid admin key pair")?;
+            let ix = m10_fx_solana::instruction::add_fx_feed(admin.pubkey(), feed.fx_feed());
+            let signature = update_registry(fx, &admin, &feed.payer, ix)?;
+            report!(output, "Added FX feed {}", feed.feed);
+            output.result(feed.result(&signature));
+        }
+        Rpc::UpdateFeed(feed) => {
+            report!(output, "{:?}", feed);
+            let admin = read_keypair_file(&feed.admin).context("Invalid admin key pair")?;
+            let ix = m10_fx_solana::instruction::update_fx_feed(admin.pubkey(), feed.fx_feed());
+            let signature = update_registry(fx, &admin, &feed.payer, ix)?;
+            report!(output, "Updated FX feed {}", feed.feed);
+            output.result(feed.result(&signature));
+        }
+        Rpc::SetBaseMint(base_mint) => {
+            report!(output, "{:?}", base_mint);
+            let admin = read_keypair_file(&base_mint.admin).context("Invalid admin key pair")?;
+            let ix = m10_fx_solana::instruction::set_base_mint(admin.pubkey(), base_mint.base_mint);
+            let signature = update_registry(fx, &admin, &base_mint.payer, ix)?;
+            report!(output, "Set base mint to {:?}", base_mint.base_mint);
+            output.result(json!({
+                "signature": signature.to_string(),
+                "base_mint": base_mint.base_mint.map(|mint| mint.to_string()),
+            }));
+        }
+        Rpc::RemoveFeed(feed) => {
+            report!(output, "{:?}", feed);
+            let admin = read_keypair_file(&feed.admin).context("Invalid admin key pair")?;
+            let ix = m10_fx_solana::instruction::remove_fx_feed(
+                admin.pubkey(),
+                feed.from_mint,
+                feed.to_mint,
+            );
+            let signature = update_registry(fx, &admin, &feed.payer, ix)?;
+            report!(
+                output,
+                "Removed FX feed {} -> {}",
+                feed.from_mint,
+                feed.to_mint
+            );
+            output.result(json!({
+                "signature": signature.to_string(),
+                "from_mint": feed.from_mint.to_string(),
+                "to_mint": feed.to_mint.to_string(),
+            }));
+        }
+        Rpc::Migrate(migrate) => {
+            report!(output, "{:?}", migrate);
+            let payer = read_keypair_file(&migrate.payer).context("Could not read payer key")?;
+            let liquidity =
+                read_keypair_file(&migrate.liquidity).context("Could not read liquidity key")?;
+            let from_mint = fx
+                .token_account(&liquidity.pubkey())
+                .context("Could not retrieve account")?
+                .mint;
+            let ix = m10_fx_solana::instruction::migrate(
+                payer.pubkey(),
+                migrate.fx_account,
+                liquidity.pubkey(),
+                from_mint,
+            );
+
+            let signature = fx
+                .send(&[ix], &payer, &[&liquidity])
+                .context("Could not migrate the FX account")?;
+            report!(output, "Migrated FX account {}", migrate.fx_account);
+            output.result(json!({
+                "signature": signature.to_string(),
+                "fx_account": migrate.fx_account.to_string(),
+            }));
+        }
+        Rpc::Refund(refund) => {
+            report!(output, "{:?}", refund);
+            let payer = read_keypair_file(&refund.payer).context("Could not read payer key")?;
+
+            let fx_data = fx
+                .fetch_swap(&refund.fx_account)
+                .context("Could not retrieve FX account")?;
+            let ix = m10_fx_solana::instruction::refund(
+                fx_data.initializer,
+                fx_data.refund_account,
+                refund.fx_account,
+            );
 
-    fn try_execute(
-        client: &RpcClient,
-        payer: &Keypair,
-        execute: &Execute,
-    ) -> Result<(), ClientError> {
-        let fx_account = client
-            .get_account(&execute.fx_account)
-            .expect("Could not retrieve FX account");
-        let fx_data = FxData::unpack(fx_account.data()).expect("invalid FX data");
-        if !fx_data.is_initialized {
-            panic!("Fx data has not yet been initialized");
+            let signature = fx
+                .send(&[ix], &payer, &[])
+                .context("Could not refund the FX swap")?;
+            report!(
+                output,
+                "Refunded {} to {}",
+                fx_data.amount_remaining(),
+                fx_data.refund_account
+            );
+            output.result(json!({
+                "signature": signature.to_string(),
+                "fx_account": refund.fx_account.to_string(),
+                "refund_account": fx_data.refund_account.to_string(),
+                "amount": fx_data.amount_remaining(),
+            }));
+        }
+        Rpc::SetFees(fees) => {
+            report!(output, "{:?}", fees);
+            let admin = read_keypair_file(&fees.admin).context("Invalid admin key pair")?;
+            let ix = m10_fx_solana::instruction::set_fees(
+                admin.pubkey(),
+                fees.fee_bps,
+                fees.spread_bps,
+                fees.fee_collector,
+            );
+            let signature = update_registry(fx, &admin, &fees.payer, ix)?;
+            report!(
+                output,
+                "Set fee to {} bps & spread to {} bps",
+                fees.fee_bps,
+                fees.spread_bps
+            );
+            output.result(json!({
+                "signature": signature.to_string(),
+                "fee_bps": fees.fee_bps,
+                "spread_bps": fees.spread_bps,
+                "fee_collector": fees.fee_collector.to_string(),
+            }));
         }
+        Rpc::Keeper(keeper) => {
+            report!(output, "{:?}", keeper);
+            let payer = read_keypair_file(&keeper.payer).context("Could not read payer key")?;
+            keeper::run(fx, &payer, &keeper, output);
+        }
+        Rpc::Show(show) => {
+            let fx_data = fx
+                .fetch_swap(&show.fx_account)
+                .context("Could not retrieve FX account")?;
+            let now = fx.cluster_time().context("Could not get cluster time")?;
 
-        let liquidity_key =
-            read_keypair_file(&execute.liquidity).expect("Could not read liquidity key");
-        if fx_data.to_liquidity != liquidity_key.pubkey() {
-            panic!(
-                "Mismatched liquidity provider, expected {}",
-                fx_data.to_liquidity
+            report!(output, "FX account {}", show.fx_account);
+            report!(output, "Initializer {}", fx_data.initializer);
+            report!(output, "From {}", fx_data.from_holding);
+            report!(output, "To {}", fx_data.to_holding);
+            report!(
+                output,
+                "Amount {} of which {} filled",
+                spl_token::amount_to_ui_amount(fx_data.amount, fx_data.from_decimals),
+                spl_token::amount_to_ui_amount(fx_data.amount_filled, fx_data.from_decimals)
+            );
+            report!(
+                output,
+                "{:?} order with limits [{}, {}]",
+                fx_data.kind,
+                fx_data.limits.start,
+                fx_data.limits.end
+            );
+            report!(
+                output,
+                "Valid until {} ({}s left), then {:?}",
+                fx_data.valid_until,
+                (fx_data.valid_until - now).max(0),
+                fx_data.expiry
             );
+            report!(
+                output,
+                "FX feed {} of {:?}{}",
+                fx_data.fx_feed,
+                fx_data.oracle,
+                fx_data
+                    .cross_feed
+                    .map(|feed| format!(" through {}", feed))
+                    .unwrap_or_default()
+            );
+            report!(output, "Crank reward {} lamports", fx_data.crank_reward);
+            if fx_data.receipt {
+                report!(
+                    output,
+                    "Settlement receipt {}",
+                    pda_receipt(&show.fx_account).0
+                );
+            }
+            let status = fx
+                .swap_rate(&fx_data)
+                .map(|quote| (quote.rate, fx_data.check_execution(&quote, now)));
+            let reason = match &status {
+                Ok((rate, Ok(trigger))) => {
+                    report!(
+                        output,
+                        "Current exchange rate {}: executable ({:?})",
+                        rate,
+                        trigger
+                    );
+                    None
+                }
+                Ok((rate, Err(err))) => {
+                    report!(output, "Current exchange rate {}: {}", rate, err);
+                    Some(err.to_string())
+                }
+                Err(err) => {
+                    report!(output, "Could not get current FX rate: {}", err);
+                    Some(err.to_string())
+                }
+            };
+            output.result(json!({
+                "swap": swap_json(&show.fx_account, &fx_data),
+                "now": now,
+                "rate": status.as_ref().ok().map(|(rate, _)| rate.to_string()),
+                "executable": reason.is_none(),
+                "reason": reason,
+            }));
+        }
+        Rpc::List(list) => {
+            let filter = SwapFilter {
+                initializer: list.initializer,
+                to: list.to,
+            };
+            let swaps = fx
+                .list_swaps(&filter)
+                .context("Could not retrieve FX accounts")?;
+            for (fx_account, fx_data) in &swaps {
+                report!(
+                    output,
+                    "{} {} -> {}: {} of {} filled, limits [{}, {}], valid until {}",
+                    fx_account,
+                    fx_data.initializer,
+                    fx_data.to_holding,
+                    fx_data.amount_filled,
+                    fx_data.amount,
+                    fx_data.limits.start,
+                    fx_data.limits.end,
+                    fx_data.valid_until
+                );
+            }
+            report!(output, "Found {} FX swaps", swaps.len());
+            output.result(json!({
+                "swaps": swaps
+                    .iter()
+                    .map(|(fx_account, fx_data)| swap_json(fx_account, fx_data))
+                    .collect::<Vec<_>>(),
+            }));
         }
+        Rpc::Receipt(receipt) => {
+            let (receipt_key, _) = pda_receipt(&receipt.fx_account);
+            let receipt_data = fx
+                .fetch_receipt(&receipt.fx_account)
+                .context("No settlement receipt recorded for the FX account")?;
 
-        let execute_ix = m10_fx_solana::instruction::execute(
-            fx_data.initializer,
-            fx_data.to_holding,
-            fx_data.to_liquidity,
-            execute.fx_account,
-            fx_data.fx_feed,
-        );
-
-        // get a blockhash
-        let recent_blockhash = client
-            .get_latest_blockhash()
-            .expect("error: unable to get recent blockhash");
-
-        // Execute transactions
-        let tx = Transaction::new_signed_with_payer(
-            &[execute_ix],
-            Some(&payer.pubkey()),
-            &[&liquidity_key, &payer],
-            recent_blockhash,
-        );
-        client.send_and_confirm_transaction_with_spinner_and_commitment(
-            &tx,
-            CommitmentConfig::processed(),
-        )?;
-        Ok(())
+            report!(output, "Settlement receipt {}", receipt_key);
+            report!(output, "FX account {}", receipt.fx_account);
+            report!(output, "Initializer {}", receipt_data.initializer);
+            report!(output, "From {}", receipt_data.from_holding);
+            report!(output, "To {}", receipt_data.to_holding);
+            report!(
+                output,
+                "Settled {} for {} paid out",
+                receipt_data.amount_in,
+                receipt_data.amount_out
+            );
+            report!(
+                output,
+                "Last executed at {} on {:?}, at rate {} of round {}{}",
+                receipt_data.timestamp,
+                receipt_data.reason,
+                receipt_data.rate,
+                receipt_data.round_id,
+                receipt_data
+                    .cross_round_id
+                    .map(|round_id| format!(" & cross round {}", round_id))
+                    .unwrap_or_default()
+            );
+            output.result(json!({
+                "receipt": receipt_key.to_string(),
+                "fx_account": receipt.fx_account.to_string(),
+                "initializer": receipt_data.initializer.to_string(),
+                "from": receipt_data.from_holding.to_string(),
+                "to": receipt_data.to_holding.to_string(),
+                "amount_in": receipt_data.amount_in,
+                "amount_out": receipt_data.amount_out,
+                "rate": receipt_data.rate.to_string(),
+                "round_id": receipt_data.round_id,
+                "cross_round_id": receipt_data.cross_round_id,
+                "reason": format!("{:?}", receipt_data.reason),
+                "timestamp": receipt_data.timestamp,
+            }));
+        }
     }
+    Ok(())
 }
 
-#[derive(Default)]
-struct FakeAccounts {
-    data: [u8; 0],
-    lamports: u64,
+fn update_registry(
+    fx: &FxClient,
+    admin: &Keypair,
+    payer: &Path,
+    ix: Instruction,
+) -> Result<Signature, String> {
+    let payer = read_keypair_file(payer).context("Could not read payer key")?;
+    fx.send(&[ix], &payer, &[admin])
+        .context("Could not update the registry")
 }
 
-impl FakeAccounts {
-    fn info<'a>(&'a mut self, public_key: &'a Pubkey) -> AccountInfo<'a> {
-        AccountInfo {
-            key: &public_key,
-            is_signer: false,
-            is_writable: false,
-            lamports: Rc::new(RefCell::new(&mut self.lamports)),
-            data: Rc::new(RefCell::new(&mut self.data)),
-            owner: &public_key,
-            executable: false,
-            rent_epoch: 0,
+/// The machine-readable fields of the swap in [`fx_account`]
+fn swap_json(fx_account: &Pubkey, fx_data: &FxData) -> Value {
+    json!({
+        "fx_account": fx_account.to_string(),
+        "initializer": fx_data.initializer.to_string(),
+        "from": fx_data.from_holding.to_string(),
+        "to": fx_data.to_holding.to_string(),
+        "refund_account": fx_data.refund_account.to_string(),
+        "amount": fx_data.amount,
+        "amount_filled": fx_data.amount_filled,
+        "kind": format!("{:?}", fx_data.kind),
+        "limits": {
+            "lower": fx_data.limits.start.to_string(),
+            "upper": fx_data.limits.end.to_string(),
+        },
+        "valid_until": fx_data.valid_until,
+        "expiry": format!("{:?}", fx_data.expiry),
+        "oracle": format!("{:?}", fx_data.oracle),
+        "fx_feed": fx_data.fx_feed.to_string(),
+        "cross_feed": fx_data.cross_feed.map(|feed| feed.to_string()),
+        "crank_reward": fx_data.crank_reward,
+        "receipt": fx_data.receipt,
+    })
+}
+
+impl Feed {
+    fn result(&self, signature: &Signature) -> Value {
+        json!({
+            "signature": signature.to_string(),
+            "from_mint": self.from_mint.to_string(),
+            "to_mint": self.to_mint.to_string(),
+            "feed": self.feed.to_string(),
+            "oracle": format!("{:?}", self.oracle),
+            "inverted": self.inverted,
+            "max_age": self.max_age,
+        })
+    }
+
+    fn fx_feed(&self) -> FxFeed {
+        FxFeed {
+            from_mint: self.from_mint,
+            to_mint: self.to_mint,
+            feed: self.feed,
+            oracle_program: self.oracle_program,
+            oracle: self.oracle,
+            inverted: self.inverted,
+            max_age: self.max_age,
         }
     }
 }
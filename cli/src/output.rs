@@ -0,0 +1,50 @@
+//! How the CLI reports the outcome of its commands
+use serde_json::{json, Value};
+use std::fmt::Display;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Output {
+    /// Human readable text
+    Text,
+    /// A JSON object per result, while the text is written to stderr
+    Json,
+}
+
+impl Output {
+    /// Print the machine-readable [`result`] of a command
+    pub fn result(self, result: Value) {
+        if self == Output::Json {
+            println!("{}", result);
+        }
+    }
+
+    /// Report the [`error`] failing a command, as a JSON object when results are JSON
+    pub fn error(self, error: &str) {
+        match self {
+            Output::Text => eprintln!("Error: {}", error),
+            Output::Json => println!("{}", json!({ "error": error })),
+        }
+    }
+}
+
+/// Describe what failed, reporting the error through [`Output::error`] rather than panicking
+pub trait Context<T> {
+    fn context(self, what: &str) -> Result<T, String>;
+}
+
+impl<T, E: Display> Context<T> for Result<T, E> {
+    fn context(self, what: &str) -> Result<T, String> {
+        self.map_err(|err| format!("{}: {}", what, err))
+    }
+}
+
+/// Report text to the user, keeping it out of stdout when that carries JSON
+macro_rules! report {
+    ($output:expr, $($arg:tt)*) => {
+        match $output {
+            $crate::output::Output::Text => println!($($arg)*),
+            $crate::output::Output::Json => eprintln!($($arg)*),
+        }
+    };
+}
+pub(crate) use report;
@@ -0,0 +1,40 @@
+use solana_client::rpc_client::RpcClient;
+use solana_program::account_info::AccountInfo;
+use solana_program::pubkey::Pubkey;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Off-chain copy of an account, letting the program's rate evaluation run against it
+#[derive(Default)]
+pub(crate) struct FakeAccounts {
+    data: Vec<u8>,
+    lamports: u64,
+    owner: Pubkey,
+}
+
+impl FakeAccounts {
+    /// Mirror the on-chain account, if it exists
+    pub(crate) fn fetch(client: &RpcClient, public_key: &Pubkey) -> Self {
+        client
+            .get_account(public_key)
+            .map(|account| FakeAccounts {
+                data: account.data,
+                lamports: account.lamports,
+                owner: account.owner,
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn info<'a>(&'a mut self, public_key: &'a Pubkey) -> AccountInfo<'a> {
+        AccountInfo {
+            key: public_key,
+            is_signer: false,
+            is_writable: false,
+            lamports: Rc::new(RefCell::new(&mut self.lamports)),
+            data: Rc::new(RefCell::new(&mut self.data[..])),
+            owner: &self.owner,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+}
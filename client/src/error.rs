@@ -0,0 +1,83 @@
+use m10_fx_solana::error::FxError;
+use m10_fx_solana::rates::Oracle;
+use num_traits::FromPrimitive;
+use solana_client::client_error::ClientError;
+use solana_program::instruction::InstructionError;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::transaction::TransactionError;
+use thiserror::Error;
+
+pub type FxClientResult<T> = Result<T, FxClientError>;
+
+#[derive(Error, Debug)]
+pub enum FxClientError {
+    /// The program refused the request, on-chain or when evaluated off-chain.
+    /// Custom errors of the SPL token program share the same codes, so a failed token transfer may be reported as such.
+    #[error(transparent)]
+    Fx(FxError),
+    #[error(transparent)]
+    Program(ProgramError),
+    #[error(transparent)]
+    Rpc(Box<ClientError>),
+    #[error("Account {0} not found")]
+    AccountNotFound(Pubkey),
+    #[error("No liquidity provider registered for mint {0}")]
+    NoLiquidity(Pubkey),
+    #[error("No FX feed registered for {0} -> {1}")]
+    NoFxFeed(Pubkey, Pubkey),
+    #[error("No fee account of {0} for mint {1}")]
+    NoFeeAccount(Pubkey, Pubkey),
+    /// The oracle only returns the rate of the feed to the program, through a CPI
+    #[error("The {0:?} rate of FX feed {1} can only be read on-chain")]
+    OnChainRate(Oracle, Pubkey),
+}
+
+impl FxClientError {
+    /// Whether the program refused the transaction, rather than its submission failing
+    pub fn is_rejected(&self) -> bool {
+        match self {
+            FxClientError::Fx(_) => true,
+            FxClientError::Rpc(err) => matches!(
+                err.get_transaction_error(),
+                Some(TransactionError::InstructionError(
+                    _,
+                    InstructionError::Custom(_) | InstructionError::InsufficientFunds
+                ))
+            ),
+            _ => false,
+        }
+    }
+}
+
+impl From<FxError> for FxClientError {
+    fn from(err: FxError) -> Self {
+        FxClientError::Fx(err)
+    }
+}
+
+impl From<ProgramError> for FxClientError {
+    fn from(err: ProgramError) -> Self {
+        match err {
+            ProgramError::Custom(code) => match FxError::from_u32(code) {
+                Some(err) => FxClientError::Fx(err),
+                None => FxClientError::Program(err),
+            },
+            err => FxClientError::Program(err),
+        }
+    }
+}
+
+impl From<ClientError> for FxClientError {
+    fn from(err: ClientError) -> Self {
+        match err.get_transaction_error() {
+            Some(TransactionError::InstructionError(_, InstructionError::Custom(code))) => {
+                match FxError::from_u32(code) {
+                    Some(err) => FxClientError::Fx(err),
+                    None => FxClientError::Rpc(Box::new(err)),
+                }
+            }
+            _ => FxClientError::Rpc(Box::new(err)),
+        }
+    }
+}
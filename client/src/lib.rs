@@ -0,0 +1,445 @@
+//! Client of the FX swap program over a Solana RPC node.
+//! It resolves the registries, liquidity providers & FX feeds a swap needs, creates its holding account,
+//! then signs & submits the transactions, decoding the program's errors.
+mod accounts;
+pub mod error;
+
+use crate::accounts::FakeAccounts;
+pub use crate::error::{FxClientError, FxClientResult};
+use m10_fx_solana::liquidity::LiquidityProvider;
+use m10_fx_solana::rates::{convert_amount, feed_for_token, CrossRateFx, FxRate, FxRoute, Oracle};
+use m10_fx_solana::state::{
+    Charges, ExpiryPolicy, FxConfig, FxData, FxFeedRegistry, LiquidityRegistry, SettlementReceipt,
+    SwapKind, FX_DATA_INITIALIZER_OFFSET, FX_DATA_TO_HOLDING_OFFSET,
+};
+use m10_fx_solana::utils::{
+    pda_config, pda_feed_registry, pda_fx_account, pda_liquidity_registry, pda_receipt, pda_vault,
+};
+use rust_decimal::Decimal;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_client::rpc_request::TokenAccountsFilter;
+use solana_program::clock::{Clock, UnixTimestamp};
+use solana_program::instruction::Instruction;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_instruction::create_account;
+use solana_program::sysvar;
+use solana_sdk::account::Account;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use spl_token::state::{Account as TokenAccount, Mint};
+use std::ops::Range;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A swap to initiate, see [`FxClient::initiate_swap`]
+#[derive(Debug, Clone)]
+pub struct SwapRequest {
+    /// Token account funding the swap, owned by the initializer
+    pub from: Pubkey,
+    /// Token account receiving the converted amount
+    pub to: Pubkey,
+    /// Token account refunded on expiry, usually the [`from`] account
+    pub refund_account: Pubkey,
+    pub amount: u64,
+    pub limits: Range<Decimal>,
+    /// Defaults to the program's validity
+    pub valid_for: Option<Duration>,
+    pub kind: SwapKind,
+    pub expiry: ExpiryPolicy,
+    /// Nonce deriving the FX account, defaults to the time of initiation in nanoseconds.
+    /// Reusing the nonce of a closed swap reuses its FX account, vault & receipt addresses.
+    pub nonce: Option<u64>,
+    /// Lamports rewarding whoever executes the swap
+    pub crank_reward: u64,
+    /// Record the swap's executions in a settlement receipt
+    pub receipt: bool,
+}
+
+/// A swap initiated by [`FxClient::initiate_swap`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitiatedSwap {
+    pub signature: Signature,
+    pub fx_account: Pubkey,
+    pub nonce: u64,
+    /// Token account holding the funds until they're escrowed
+    pub holding: Pubkey,
+    /// The swap's escrow token account
+    pub vault: Pubkey,
+}
+
+/// Current conversion of an amount between two tokens, see [`FxClient::quote`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quote {
+    pub route: FxRoute,
+    pub rate: FxRate,
+    pub from_decimals: u8,
+    pub to_decimals: u8,
+    /// Converted amount, before the fee & spread
+    pub quoted: u64,
+    pub charges: Charges,
+}
+
+/// Narrows down the swaps of [`FxClient::list_swaps`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SwapFilter {
+    pub initializer: Option<Pubkey>,
+    pub to: Option<Pubkey>,
+}
+
+pub struct FxClient {
+    client: RpcClient,
+}
+
+impl FxClient {
+    pub fn new(client: RpcClient) -> Self {
+        FxClient { client }
+    }
+
+    /// The underlying RPC client
+    pub fn rpc(&self) -> &RpcClient {
+        &self.client
+    }
+
+    /// Sign the [`instructions`] by the [`payer`] & the other [`signers`], then submit them in a transaction
+    pub fn send(
+        &self,
+        instructions: &[Instruction],
+        payer: &dyn Signer,
+        signers: &[&dyn Signer],
+    ) -> FxClientResult<Signature> {
+        let recent_blockhash = self.client.get_latest_blockhash()?;
+        let mut all_signers = vec![payer];
+        all_signers.extend_from_slice(signers);
+        let tx = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            &all_signers,
+            recent_blockhash,
+        );
+        Ok(self.client.send_and_confirm_transaction(&tx)?)
+    }
+
+    /// The cluster's time, as the program observes it
+    pub fn cluster_time(&self) -> FxClientResult<UnixTimestamp> {
+        let account = self.account(&sysvar::clock::id())?;
+        let clock: Clock = solana_sdk::account::from_account(&account)
+            .ok_or(FxClientError::AccountNotFound(sysvar::clock::id()))?;
+        Ok(clock.unix_timestamp)
+    }
+
+    /// The program's fee configuration, charging nothing until it's set
+    pub fn config(&self) -> FxClientResult<FxConfig> {
+        match self.try_account(&pda_config().0)? {
+            Some(account) => Ok(FxConfig::unpack(&account.data)?),
+            None => Ok(FxConfig::default()),
+        }
+    }
+
+    /// The swap held in [`fx_account`]
+    pub fn fetch_swap(&self, fx_account: &Pubkey) -> FxClientResult<FxData> {
+        Ok(FxData::unpack(&self.account(fx_account)?.data)?)
+    }
+
+    /// The settlement receipt of the swap held in [`fx_account`], which may already be closed
+    pub fn fetch_receipt(&self, fx_account: &Pubkey) -> FxClientResult<SettlementReceipt> {
+        let (receipt, _) = pda_receipt(fx_account);
+        Ok(SettlementReceipt::unpack(&self.account(&receipt)?.data)?)
+    }
+
+    /// Every open swap matching the [`filter`]
+    pub fn list_swaps(&self, filter: &SwapFilter) -> FxClientResult<Vec<(Pubkey, FxData)>> {
+        let filters = filter
+            .initializer
+            .map(|initializer| (FX_DATA_INITIALIZER_OFFSET, initializer))
+            .into_iter()
+            .chain(filter.to.map(|to| (FX_DATA_TO_HOLDING_OFFSET, to)))
+            .map(|(offset, key)| {
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(offset, key.as_ref()))
+            })
+            .chain([RpcFilterType::DataSize(FxData::LEN as u64)])
+            .collect();
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        let accounts = self
+            .client
+            .get_program_accounts_with_config(&m10_fx_solana::id(), config)?;
+        Ok(accounts
+            .into_iter()
+            .filter_map(|(fx_account, account)| {
+                FxData::unpack(&account.data)
+                    .ok()
+                    .map(|fx_data| (fx_account, fx_data))
+            })
+            .collect())
+    }
+
+    /// Convert [`amount`] of [`from_mint`] at the current rate of the registered FX feeds
+    pub fn quote(
+        &self,
+        from_mint: &Pubkey,
+        to_mint: &Pubkey,
+        amount: u64,
+    ) -> FxClientResult<Quote> {
+        let feed_registry = FxFeedRegistry::unpack(&self.account(&pda_feed_registry().0)?.data)?;
+        let route = feed_for_token(&feed_registry, from_mint, to_mint)
+            .ok_or(FxClientError::NoFxFeed(*from_mint, *to_mint))?;
+        let rate = self.route_rate(&route)?;
+        let from_decimals = self.mint(from_mint)?.decimals;
+        let to_decimals = self.mint(to_mint)?.decimals;
+        let quoted = convert_amount(amount, rate.rate, from_decimals, to_decimals)?;
+        Ok(Quote {
+            route,
+            rate,
+            from_decimals,
+            to_decimals,
+            quoted,
+            charges: self.config()?.charges(quoted),
+        })
+    }
+
+    /// Evaluate the current exchange rate of an initiated swap off-chain, like the program does on execution.
+    /// Fails with [`FxClientError::OnChainRate`] for feeds the program reads through a CPI, e.g. of ChainLink.
+    pub fn swap_rate(&self, fx_data: &FxData) -> FxClientResult<FxRate> {
+        check_off_chain(
+            &fx_data.oracle,
+            [&fx_data.fx_feed].into_iter().chain(&fx_data.cross_feed),
+        )?;
+        let mut fake_program = FakeAccounts::default();
+        let mut fake_feed = FakeAccounts::fetch(&self.client, &fx_data.fx_feed);
+        let fx_program = fake_program.info(&fx_data.oracle_program);
+        let fx_feed = fake_feed.info(&fx_data.fx_feed);
+        let rate = match &fx_data.cross_feed {
+            Some(cross_feed) => {
+                let mut fake_cross_feed = FakeAccounts::fetch(&self.client, cross_feed);
+                CrossRateFx::rate(
+                    &fx_data.oracle,
+                    &fx_program,
+                    (&fx_feed, fx_data.fx_feed_inverted),
+                    (
+                        &fake_cross_feed.info(cross_feed),
+                        fx_data.cross_feed_inverted,
+                    ),
+                )?
+            }
+            None => fx_data
+                .oracle
+                .rate(&fx_program, &fx_feed)?
+                .directed(fx_data.fx_feed_inverted)?,
+        };
+        Ok(rate)
+    }
+
+    /// Initiate the swap of the [`initializer`]'s tokens, paid for by the [`payer`].
+    /// The amount is moved into a new holding account, escrowed by the program on initiation.
+    pub fn initiate_swap(
+        &self,
+        request: &SwapRequest,
+        initializer: &dyn Signer,
+        payer: &dyn Signer,
+    ) -> FxClientResult<InitiatedSwap> {
+        let from = self.token_account(&request.from)?;
+        let to = self.token_account(&request.to)?;
+        let liquidity_registry =
+            LiquidityRegistry::unpack(&self.account(&pda_liquidity_registry().0)?.data)?;
+        let from_liquidity = liquidity_registry
+            .liquidity_account(&from)
+            .ok_or(FxClientError::NoLiquidity(from.mint))?;
+        let feed_registry = FxFeedRegistry::unpack(&self.account(&pda_feed_registry().0)?.data)?;
+        let route = feed_for_token(&feed_registry, &from.mint, &to.mint)
+            .ok_or(FxClientError::NoFxFeed(from.mint, to.mint))?;
+
+        let nonce = request.nonce.unwrap_or_else(new_nonce);
+        let (fx_account, _) = pda_fx_account(&initializer.pubkey(), nonce);
+        // The swap's escrow vault holds the funds until execution
+        let (vault, _) = pda_vault(&fx_account);
+
+        let holding = Keypair::new();
+        let lamports = self
+            .client
+            .get_minimum_balance_for_rent_exemption(TokenAccount::LEN)?;
+        let instructions = [
+            create_account(
+                &payer.pubkey(),
+                &holding.pubkey(),
+                lamports,
+                TokenAccount::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &holding.pubkey(),
+                &from.mint,
+                &vault,
+            )?,
+            spl_token::instruction::transfer(
+                &spl_token::id(),
+                &request.from,
+                &holding.pubkey(),
+                &initializer.pubkey(),
+                &[],
+                request.amount,
+            )?,
+            m10_fx_solana::instruction::initiate(
+                initializer.pubkey(),
+                payer.pubkey(),
+                holding.pubkey(),
+                request.to,
+                request.refund_account,
+                from.mint,
+                to.mint,
+                nonce,
+                route.feed().feed,
+                route.cross_feed().map(|feed| feed.feed),
+                from_liquidity,
+                request.amount,
+                request.limits.end,
+                request.limits.start,
+                request.valid_for,
+                route.oracle(),
+                request.kind,
+                request.expiry,
+                request.crank_reward,
+                request.receipt,
+            ),
+        ];
+        let signature = self.send(&instructions, payer, &[&holding, initializer])?;
+        Ok(InitiatedSwap {
+            signature,
+            fx_account,
+            nonce,
+            holding: holding.pubkey(),
+            vault,
+        })
+    }
+
+    /// Execute the swap in [`fx_account`] for the [`cranker`], filling at most [`max_amount`].
+    /// It's paid out by the provider selected on initiate, either signing as [`liquidity`] or otherwise through its delegate.
+    pub fn execute_swap(
+        &self,
+        fx_account: &Pubkey,
+        fx_data: &FxData,
+        cranker: &dyn Signer,
+        liquidity: Option<&dyn Signer>,
+        max_amount: Option<u64>,
+    ) -> FxClientResult<Signature> {
+        // The protocol fee is collected in the received token
+        let config = self.config()?;
+        let fee_account = if config.fee_bps > 0 {
+            let to_mint = self.token_account(&fx_data.to_holding)?.mint;
+            let fee_accounts = self.client.get_token_accounts_by_owner(
+                &config.fee_collector,
+                TokenAccountsFilter::Mint(to_mint),
+            )?;
+            let fee_account = fee_accounts
+                .first()
+                .and_then(|account| Pubkey::from_str(&account.pubkey).ok())
+                .ok_or(FxClientError::NoFeeAccount(config.fee_collector, to_mint))?;
+            Some(fee_account)
+        } else {
+            None
+        };
+
+        let execute_ix = m10_fx_solana::instruction::execute(
+            cranker.pubkey(),
+            fx_data.initializer,
+            fx_data.to_holding,
+            fx_data.to_liquidity,
+            liquidity.is_none(),
+            fx_data.from_liquidity,
+            *fx_account,
+            fx_data.fx_feed,
+            fx_data.oracle_program,
+            fx_data.cross_feed,
+            fee_account,
+            max_amount,
+        );
+        let signers: Vec<&dyn Signer> = liquidity.into_iter().collect();
+        self.send(&[execute_ix], cranker, &signers)
+    }
+
+    /// Evaluate the current exchange rate along the route off-chain, read by its registered oracle
+    fn route_rate(&self, route: &FxRoute) -> FxClientResult<FxRate> {
+        let oracle = &route.oracle();
+        let feed = route.feed();
+        check_off_chain(
+            oracle,
+            [feed]
+                .into_iter()
+                .chain(route.cross_feed())
+                .map(|feed| &feed.feed),
+        )?;
+        let mut fake_program = FakeAccounts::default();
+        let mut fake_feed = FakeAccounts::fetch(&self.client, &feed.feed);
+        let fx_program = fake_program.info(&feed.oracle_program);
+        let rate = match route.cross_feed() {
+            Some(cross_feed) => {
+                let mut fake_cross_feed = FakeAccounts::fetch(&self.client, &cross_feed.feed);
+                CrossRateFx::rate(
+                    oracle,
+                    &fx_program,
+                    (&fake_feed.info(&feed.feed), feed.inverted),
+                    (&fake_cross_feed.info(&cross_feed.feed), cross_feed.inverted),
+                )?
+            }
+            None => oracle
+                .rate(&fx_program, &fake_feed.info(&feed.feed))?
+                .directed(feed.inverted)?,
+        };
+        Ok(rate)
+    }
+
+    fn try_account(&self, key: &Pubkey) -> FxClientResult<Option<Account>> {
+        Ok(self
+            .client
+            .get_account_with_commitment(key, self.client.commitment())?
+            .value)
+    }
+
+    fn account(&self, key: &Pubkey) -> FxClientResult<Account> {
+        self.try_account(key)?
+            .ok_or(FxClientError::AccountNotFound(*key))
+    }
+
+    /// The SPL token account [`key`]
+    pub fn token_account(&self, key: &Pubkey) -> FxClientResult<TokenAccount> {
+        Ok(TokenAccount::unpack(&self.account(key)?.data)?)
+    }
+
+    fn mint(&self, key: &Pubkey) -> FxClientResult<Mint> {
+        Ok(Mint::unpack(&self.account(key)?.data)?)
+    }
+}
+
+/// Check the [`oracle`] reads the rate of every one of the [`feeds`] from its account,
+/// rather than returning it through a CPI, which only the program can make
+fn check_off_chain<'a>(
+    oracle: &Oracle,
+    feeds: impl IntoIterator<Item = &'a Pubkey>,
+) -> FxClientResult<()> {
+    match feeds
+        .into_iter()
+        .find(|feed| !oracle.reads_feed_account(feed))
+    {
+        Some(feed) => Err(FxClientError::OnChainRate(*oracle, *feed)),
+        None => Ok(()),
+    }
+}
+
+/// A nonce no earlier swap of the initializer used, being the current time in nanoseconds.
+/// Unlike the first unused nonce, it never reuses the FX account of a closed swap.
+fn new_nonce() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Clock before the UNIX epoch")
+        .as_nanos() as u64
+}
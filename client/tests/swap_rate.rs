@@ -0,0 +1,89 @@
+use m10_fx_solana::rates::{Oracle, USD_TO_EUR};
+use m10_fx_solana::state::{ExpiryPolicy, FxData, SwapKind};
+use m10_fx_solana_client::{FxClient, FxClientError};
+use rust_decimal::Decimal;
+use solana_client::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+
+/// A client whose RPC requests are answered by canned responses
+fn mock_client() -> FxClient {
+    FxClient::new(RpcClient::new_mock("succeeds".to_string()))
+}
+
+fn swap(oracle: Oracle, fx_feed: Pubkey, cross_feed: Option<Pubkey>) -> FxData {
+    FxData {
+        is_initialized: true,
+        initializer: Pubkey::new_unique(),
+        from_holding: Pubkey::new_unique(),
+        to_holding: Pubkey::new_unique(),
+        from_liquidity: Pubkey::new_unique(),
+        to_liquidity: Pubkey::new_unique(),
+        amount: 100,
+        amount_filled: 0,
+        from_decimals: 2,
+        to_decimals: 2,
+        limits: Decimal::new(8, 1)..Decimal::new(10, 1),
+        valid_until: 0,
+        kind: SwapKind::Oco,
+        expiry: ExpiryPolicy::SettleAtMarket,
+        refund_account: Pubkey::new_unique(),
+        crank_reward: 0,
+        receipt: false,
+        oracle,
+        fx_feed,
+        fx_feed_inverted: false,
+        oracle_program: Pubkey::new_unique(),
+        max_rate_age: 3600,
+        cross_feed,
+        cross_feed_inverted: false,
+    }
+}
+
+#[test]
+fn chainlink_rates_are_left_to_the_program() {
+    let fx = mock_client();
+    let fx_feed = Pubkey::new_unique();
+    let err = fx
+        .swap_rate(&swap(Oracle::ChainLink, fx_feed, None))
+        .unwrap_err();
+    assert!(
+        matches!(err, FxClientError::OnChainRate(Oracle::ChainLink, feed) if feed == fx_feed),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn chainlink_cross_rates_are_left_to_the_program() {
+    let fx = mock_client();
+    let cross_feed = Pubkey::new_unique();
+    let err = fx
+        .swap_rate(&swap(Oracle::ChainLink, USD_TO_EUR, Some(cross_feed)))
+        .unwrap_err();
+    assert!(
+        matches!(err, FxClientError::OnChainRate(Oracle::ChainLink, feed) if feed == USD_TO_EUR),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn demo_feeds_falling_back_to_chainlink_are_left_to_the_program() {
+    let fx = mock_client();
+    let cross_feed = Pubkey::new_unique();
+    let err = fx
+        .swap_rate(&swap(Oracle::Demo, USD_TO_EUR, Some(cross_feed)))
+        .unwrap_err();
+    assert!(
+        matches!(err, FxClientError::OnChainRate(Oracle::Demo, feed) if feed == cross_feed),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn static_demo_rates_are_evaluated_off_chain() {
+    let fx = mock_client();
+    let rate = fx.swap_rate(&swap(Oracle::Demo, USD_TO_EUR, None)).unwrap();
+    assert_eq!(rate.rate, Decimal::new(9, 1));
+}
@@ -0,0 +1,324 @@
+//! The accounts passed to the swap instructions, validated before the processor relies on them
+use crate::error::FxError;
+use crate::utils::{pda_fx_account, pda_program_data, pda_receipt, pda_vault};
+use arrayref::{array_ref, array_refs};
+use solana_program::account_info::{next_account_info, AccountInfo};
+use solana_program::bpf_loader_upgradeable;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use std::mem::size_of;
+
+/// The accounts of [`crate::instruction::FxEvent::Initiate`]
+pub struct InitiateAccounts<'a, 'info> {
+    /// Signed the swap
+    pub initializer: &'a AccountInfo<'info>,
+    pub from_account: &'a AccountInfo<'info>,
+    pub to_account: &'a AccountInfo<'info>,
+    /// Derived from the initializer & nonce, not yet created
+    pub fx_account: &'a AccountInfo<'info>,
+    pub rent_sysvar: &'a AccountInfo<'info>,
+    /// The SPL token program
+    pub token: &'a AccountInfo<'info>,
+    pub fx_feed: &'a AccountInfo<'info>,
+    pub from_liquidity: &'a AccountInfo<'info>,
+    /// Derived from the FX account
+    pub vault: &'a AccountInfo<'info>,
+    pub liquidity_registry: &'a AccountInfo<'info>,
+    pub fx_feed_registry: &'a AccountInfo<'info>,
+    pub from_mint: &'a AccountInfo<'info>,
+    pub to_mint: &'a AccountInfo<'info>,
+    pub refund_account: &'a AccountInfo<'info>,
+    /// Signed to fund the new accounts
+    pub payer: &'a AccountInfo<'info>,
+    /// The system program
+    pub system_program: &'a AccountInfo<'info>,
+    /// Derived from the FX account
+    pub receipt: &'a AccountInfo<'info>,
+    pub cross_feed: Option<&'a AccountInfo<'info>>,
+    pub fx_bump_seed: u8,
+    pub vault_bump_seed: u8,
+}
+
+impl<'a, 'info> InitiateAccounts<'a, 'info> {
+    pub fn validate(accounts: &'a [AccountInfo<'info>], nonce: u64) -> Result<Self, ProgramError> {
+        let account_info_iter = &mut accounts.iter();
+        let initializer = next_account_info(account_info_iter)?;
+        let from_account = next_account_info(account_info_iter)?;
+        let to_account = next_account_info(account_info_iter)?;
+        let fx_account = next_account_info(account_info_iter)?;
+        let rent_sysvar = next_account_info(account_info_iter)?;
+        let token = next_account_info(account_info_iter)?;
+        let fx_feed = next_account_info(account_info_iter)?;
+        let from_liquidity = next_account_info(account_info_iter)?;
+        let vault = next_account_info(account_info_iter)?;
+        let liquidity_registry = next_account_info(account_info_iter)?;
+        let fx_feed_registry = next_account_info(account_info_iter)?;
+        let from_mint = next_account_info(account_info_iter)?;
+        let to_mint = next_account_info(account_info_iter)?;
+        let refund_account = next_account_info(account_info_iter)?;
+        let payer = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let receipt = next_account_info(account_info_iter)?;
+        let cross_feed = account_info_iter.next();
+
+        // Only the initializer can open its swaps
+        if !initializer.is_signer || !payer.is_signer {
+            return Err(FxError::MissingSignature)?;
+        }
+
+        // The FX account is derived from the initializer & nonce
+        let (fx_key, fx_bump_seed) = pda_fx_account(initializer.key, nonce);
+        if *fx_account.key != fx_key {
+            return Err(FxError::InvalidFxAccount)?;
+        }
+
+        // The funds are escrowed in the swap's own vault
+        let (vault_key, vault_bump_seed) = pda_vault(fx_account.key);
+        if *vault.key != vault_key {
+            return Err(FxError::InvalidVault)?;
+        }
+
+        if *receipt.key != pda_receipt(fx_account.key).0 {
+            return Err(FxError::InvalidReceipt)?;
+        }
+
+        check_token_program(token)?;
+        check_system_program(system_program)?;
+
+        Ok(InitiateAccounts {
+            initializer,
+            from_account,
+            to_account,
+            fx_account,
+            rent_sysvar,
+            token,
+            fx_feed,
+            from_liquidity,
+            vault,
+            liquidity_registry,
+            fx_feed_registry,
+            from_mint,
+            to_mint,
+            refund_account,
+            payer,
+            system_program,
+            receipt,
+            cross_feed,
+            fx_bump_seed,
+            vault_bump_seed,
+        })
+    }
+}
+
+/// The accounts of [`crate::instruction::FxEvent::TryExecute`]
+pub struct TryExecuteAccounts<'a, 'info> {
+    pub initializer: &'a AccountInfo<'info>,
+    pub to_account: &'a AccountInfo<'info>,
+    pub to_liquidity: &'a AccountInfo<'info>,
+    /// Owned by the program
+    pub fx_account: &'a AccountInfo<'info>,
+    /// The SPL token program
+    pub token: &'a AccountInfo<'info>,
+    pub fx_feed: &'a AccountInfo<'info>,
+    pub fx_program: &'a AccountInfo<'info>,
+    pub liquidity_registry: &'a AccountInfo<'info>,
+    pub from_liquidity: &'a AccountInfo<'info>,
+    /// Derived from the FX account
+    pub vault: &'a AccountInfo<'info>,
+    pub config: &'a AccountInfo<'info>,
+    pub fee_account: &'a AccountInfo<'info>,
+    /// Signed to collect the crank reward
+    pub cranker: &'a AccountInfo<'info>,
+    pub liquidity_authority: &'a AccountInfo<'info>,
+    /// Derived from the FX account
+    pub receipt: &'a AccountInfo<'info>,
+    /// The system program
+    pub system_program: &'a AccountInfo<'info>,
+    pub cross_feed: Option<&'a AccountInfo<'info>>,
+    pub receipt_bump_seed: u8,
+}
+
+impl<'a, 'info> TryExecuteAccounts<'a, 'info> {
+    pub fn validate(
+        program_id: &Pubkey,
+        accounts: &'a [AccountInfo<'info>],
+    ) -> Result<Self, ProgramError> {
+        let account_info_iter = &mut accounts.iter();
+        let initializer = next_account_info(account_info_iter)?;
+        let to_account = next_account_info(account_info_iter)?;
+        let to_liquidity = next_account_info(account_info_iter)?;
+        let fx_account = next_account_info(account_info_iter)?;
+        let token = next_account_info(account_info_iter)?;
+        let fx_feed = next_account_info(account_info_iter)?;
+        let fx_program = next_account_info(account_info_iter)?;
+        let liquidity_registry = next_account_info(account_info_iter)?;
+        let from_liquidity = next_account_info(account_info_iter)?;
+        let vault = next_account_info(account_info_iter)?;
+        let config = next_account_info(account_info_iter)?;
+        let fee_account = next_account_info(account_info_iter)?;
+        let cranker = next_account_info(account_info_iter)?;
+        let liquidity_authority = next_account_info(account_info_iter)?;
+        let receipt = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let cross_feed = account_info_iter.next();
+
+        // Anyone can execute the swap, but needs to sign for the crank reward
+        if !cranker.is_signer {
+            return Err(FxError::MissingSignature)?;
+        }
+
+        check_fx_account(program_id, fx_account)?;
+        check_vault(fx_account, vault)?;
+        check_token_program(token)?;
+        check_system_program(system_program)?;
+
+        // The receipt outlives the swap, so it's derived from the FX account
+        let (receipt_key, receipt_bump_seed) = pda_receipt(fx_account.key);
+        if *receipt.key != receipt_key {
+            return Err(FxError::InvalidReceipt)?;
+        }
+
+        Ok(TryExecuteAccounts {
+            initializer,
+            to_account,
+            to_liquidity,
+            fx_account,
+            token,
+            fx_feed,
+            fx_program,
+            liquidity_registry,
+            from_liquidity,
+            vault,
+            config,
+            fee_account,
+            cranker,
+            liquidity_authority,
+            receipt,
+            system_program,
+            cross_feed,
+            receipt_bump_seed,
+        })
+    }
+}
+
+/// The accounts of [`crate::instruction::FxEvent::Cancel`] & [`crate::instruction::FxEvent::Refund`]
+pub struct CloseAccounts<'a, 'info> {
+    pub initializer: &'a AccountInfo<'info>,
+    pub refund_account: &'a AccountInfo<'info>,
+    /// Derived from the FX account
+    pub vault: &'a AccountInfo<'info>,
+    /// Owned by the program
+    pub fx_account: &'a AccountInfo<'info>,
+    /// The SPL token program
+    pub token: &'a AccountInfo<'info>,
+}
+
+impl<'a, 'info> CloseAccounts<'a, 'info> {
+    pub fn validate(
+        program_id: &Pubkey,
+        accounts: &'a [AccountInfo<'info>],
+    ) -> Result<Self, ProgramError> {
+        let account_info_iter = &mut accounts.iter();
+        let initializer = next_account_info(account_info_iter)?;
+        let refund_account = next_account_info(account_info_iter)?;
+        let vault = next_account_info(account_info_iter)?;
+        let fx_account = next_account_info(account_info_iter)?;
+        let token = next_account_info(account_info_iter)?;
+
+        check_fx_account(program_id, fx_account)?;
+        check_vault(fx_account, vault)?;
+        check_token_program(token)?;
+
+        Ok(CloseAccounts {
+            initializer,
+            refund_account,
+            vault,
+            fx_account,
+            token,
+        })
+    }
+}
+
+/// Only the program's upgrade authority, recorded in its [`program_data`], creates the program's
+/// registries & fee configuration. Their recorded administrator manages them from then on.
+pub(crate) fn check_upgrade_authority(
+    admin: &AccountInfo,
+    program_data: &AccountInfo,
+) -> Result<(), FxError> {
+    if !admin.is_signer {
+        return Err(FxError::MissingSignature);
+    }
+    if *program_data.key != pda_program_data().0
+        || *program_data.owner != bpf_loader_upgradeable::id()
+    {
+        return Err(FxError::Unauthorized);
+    }
+    let data = program_data
+        .try_borrow_data()
+        .map_err(|_| FxError::Unauthorized)?;
+    if data.len() < PROGRAM_DATA_METADATA_LEN {
+        return Err(FxError::Unauthorized);
+    }
+    let (state, _slot, authority) = array_refs![
+        array_ref![data, 0, PROGRAM_DATA_METADATA_LEN],
+        size_of::<u32>(),
+        size_of::<u64>(),
+        size_of::<u8>() + size_of::<Pubkey>()
+    ];
+    let (has_authority, authority) = array_refs![authority, size_of::<u8>(), size_of::<Pubkey>()];
+    if u32::from_le_bytes(*state) != PROGRAM_DATA_STATE
+        || *has_authority != [1]
+        || authority != admin.key.as_ref()
+    {
+        return Err(FxError::Unauthorized);
+    }
+    Ok(())
+}
+
+/// Check the [`admin`] signed as the recorded administrator of a registry or the fee configuration
+pub(crate) fn check_admin(admin: &AccountInfo, recorded_admin: &Pubkey) -> Result<(), FxError> {
+    if !admin.is_signer {
+        return Err(FxError::MissingSignature);
+    }
+    if admin.key != recorded_admin {
+        return Err(FxError::Unauthorized);
+    }
+    Ok(())
+}
+
+/// Bincode tag of the `UpgradeableLoaderState::ProgramData` variant
+const PROGRAM_DATA_STATE: u32 = 3;
+
+/// Length of the state, slot & optional upgrade authority preceding the program in a ProgramData account
+const PROGRAM_DATA_METADATA_LEN: usize =
+    size_of::<u32>() + size_of::<u64>() + size_of::<u8>() + size_of::<Pubkey>();
+
+/// Swaps are only read from accounts written by the program
+fn check_fx_account(program_id: &Pubkey, fx_account: &AccountInfo) -> Result<(), FxError> {
+    if fx_account.owner != program_id {
+        return Err(FxError::InvalidFxAccount);
+    }
+    Ok(())
+}
+
+fn check_vault(fx_account: &AccountInfo, vault: &AccountInfo) -> Result<(), FxError> {
+    if *vault.key != pda_vault(fx_account.key).0 {
+        return Err(FxError::InvalidVault);
+    }
+    Ok(())
+}
+
+fn check_token_program(token: &AccountInfo) -> Result<(), FxError> {
+    if *token.key != spl_token::id() {
+        return Err(FxError::InvalidTokenProgram);
+    }
+    Ok(())
+}
+
+fn check_system_program(system: &AccountInfo) -> Result<(), FxError> {
+    if !system_program::check_id(system.key) {
+        return Err(FxError::InvalidSystemProgram);
+    }
+    Ok(())
+}
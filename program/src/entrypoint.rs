@@ -20,8 +20,6 @@ fn process_instruction(
         accounts.len(),
         instruction_data
     );
-    FxSwap::process(program_id, accounts, instruction_data).map_err(|err| {
-        err.print::<FxError>();
-        err
-    })
+    FxSwap::process(program_id, accounts, instruction_data)
+        .inspect_err(|err| err.print::<FxError>())
 }
@@ -23,6 +23,32 @@ pub enum FxError {
     NoLiquidity,
     #[error("Swap conditions not met")]
     SwapConditionsNotMet,
+    #[error("Unauthorized")]
+    Unauthorized,
+    #[error("Registry is full")]
+    RegistryFull,
+    #[error("Stale FX rate")]
+    StaleRate,
+    #[error("Invalid FX rate")]
+    InvalidRate,
+    #[error("Unknown account version")]
+    UnknownVersion,
+    #[error("Account requires migration")]
+    MigrationRequired,
+    #[error("Swap expired")]
+    SwapExpired,
+    #[error("Invalid FX account")]
+    InvalidFxAccount,
+    #[error("Invalid escrow vault")]
+    InvalidVault,
+    #[error("Invalid token program")]
+    InvalidTokenProgram,
+    #[error("Invalid system program")]
+    InvalidSystemProgram,
+    #[error("Mint does not match the token account")]
+    MintMismatch,
+    #[error("Invalid settlement receipt")]
+    InvalidReceipt,
 }
 
 pub type FxResult<T> = Result<T, FxError>;
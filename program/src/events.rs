@@ -0,0 +1,177 @@
+//! Structured events logged through `sol_log_data`, so indexers can replay the swaps from the transaction logs.
+//! Every event is logged as two fields: its 8-byte discriminator & its Borsh serialization.
+use crate::state::{ExpiryPolicy, SwapKind, Trigger};
+use borsh::{BorshDeserialize as Deserialize, BorshSerialize as Serialize};
+use rust_decimal::Decimal;
+use solana_program::clock::UnixTimestamp;
+use solana_program::log::sol_log_data;
+use solana_program::pubkey::Pubkey;
+
+/// Prefix of the log messages written by `sol_log_data`
+pub const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+/// An event logged by the program
+pub trait Event: Serialize {
+    /// Identifies the event type in the logs, never reused or changed
+    const DISCRIMINATOR: [u8; 8];
+
+    fn emit(&self) {
+        let mut data = Vec::new();
+        // Serializing into a `Vec` can't fail
+        Serialize::serialize(self, &mut data).expect("Failed to serialize event");
+        sol_log_data(&[&Self::DISCRIMINATOR, &data]);
+    }
+}
+
+/// A swap was initiated & its funds escrowed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SwapInitiated {
+    pub fx_account: Pubkey,
+    pub initializer: Pubkey,
+    pub from_holding: Pubkey,
+    pub to_holding: Pubkey,
+    pub amount: u64,
+    pub lower_limit: Decimal,
+    pub upper_limit: Decimal,
+    pub valid_until: UnixTimestamp,
+    pub kind: SwapKind,
+    pub expiry: ExpiryPolicy,
+}
+
+impl Event for SwapInitiated {
+    const DISCRIMINATOR: [u8; 8] = *b"swapinit";
+}
+
+/// (Part of) a swap was settled. The swap is closed once its [`amount_filled`] reaches its amount.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SwapExecuted {
+    pub fx_account: Pubkey,
+    pub to_holding: Pubkey,
+    pub rate: Decimal,
+    // Feed round which provided the rate
+    pub round_id: u64,
+    // Round of the second feed of a cross rate
+    pub cross_round_id: Option<u64>,
+    // Escrowed amount released to the liquidity provider
+    pub amount_in: u64,
+    // Amount paid to the [`to_holding`], net of the fee & spread
+    pub amount_out: u64,
+    pub fee: u64,
+    pub spread: u64,
+    // Part of the swap settled so far, including this execution
+    pub amount_filled: u64,
+    pub reason: Trigger,
+}
+
+impl Event for SwapExecuted {
+    const DISCRIMINATOR: [u8; 8] = *b"swapexec";
+}
+
+/// An execution was attempted before the swap's conditions were met.
+/// It's logged right before the execution fails, so it only appears in the logs of failed transactions,
+/// which many indexers skip.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SwapConditionsNotMet {
+    pub fx_account: Pubkey,
+    pub rate: Decimal,
+    pub lower_limit: Decimal,
+    pub upper_limit: Decimal,
+    pub valid_until: UnixTimestamp,
+    pub now: UnixTimestamp,
+}
+
+impl Event for SwapConditionsNotMet {
+    const DISCRIMINATOR: [u8; 8] = *b"swapwait";
+}
+
+/// The initializer cancelled a swap, closing it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SwapCancelled {
+    pub fx_account: Pubkey,
+    pub initializer: Pubkey,
+    pub refund_account: Pubkey,
+    // Unsettled part of the swap returned to the [`refund_account`]
+    pub amount_refunded: u64,
+}
+
+impl Event for SwapCancelled {
+    const DISCRIMINATOR: [u8; 8] = *b"swapcanc";
+}
+
+/// An expired swap was refunded, closing it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SwapRefunded {
+    pub fx_account: Pubkey,
+    pub initializer: Pubkey,
+    pub refund_account: Pubkey,
+    // Unsettled part of the swap returned to the [`refund_account`]
+    pub amount_refunded: u64,
+    pub valid_until: UnixTimestamp,
+}
+
+impl Event for SwapRefunded {
+    const DISCRIMINATOR: [u8; 8] = *b"swaprefd";
+}
+
+/// Any of the events logged by the program
+#[derive(Debug, Clone, PartialEq)]
+pub enum FxLog {
+    SwapInitiated(SwapInitiated),
+    SwapExecuted(SwapExecuted),
+    SwapConditionsNotMet(SwapConditionsNotMet),
+    SwapCancelled(SwapCancelled),
+    SwapRefunded(SwapRefunded),
+}
+
+impl FxLog {
+    /// Decode the fields logged by `sol_log_data`, if they hold a known event
+    pub fn decode(fields: &[&[u8]]) -> Option<Self> {
+        let (discriminator, data) = match fields {
+            [discriminator, data] => (*discriminator, *data),
+            _ => return None,
+        };
+        if discriminator == SwapInitiated::DISCRIMINATOR {
+            SwapInitiated::try_from_slice(data)
+                .ok()
+                .map(FxLog::SwapInitiated)
+        } else if discriminator == SwapExecuted::DISCRIMINATOR {
+            SwapExecuted::try_from_slice(data)
+                .ok()
+                .map(FxLog::SwapExecuted)
+        } else if discriminator == SwapConditionsNotMet::DISCRIMINATOR {
+            SwapConditionsNotMet::try_from_slice(data)
+                .ok()
+                .map(FxLog::SwapConditionsNotMet)
+        } else if discriminator == SwapCancelled::DISCRIMINATOR {
+            SwapCancelled::try_from_slice(data)
+                .ok()
+                .map(FxLog::SwapCancelled)
+        } else if discriminator == SwapRefunded::DISCRIMINATOR {
+            SwapRefunded::try_from_slice(data)
+                .ok()
+                .map(FxLog::SwapRefunded)
+        } else {
+            None
+        }
+    }
+
+    /// Decode a "Program data: " line of a transaction's log messages, if it holds a known event
+    pub fn from_log_message(message: &str) -> Option<Self> {
+        let fields = message
+            .strip_prefix(PROGRAM_DATA_PREFIX)?
+            .split(' ')
+            .map(base64::decode)
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        let fields: Vec<&[u8]> = fields.iter().map(Vec::as_slice).collect();
+        Self::decode(&fields)
+    }
+
+    /// Decode all the events of a transaction's log messages, in the order they were logged
+    pub fn from_log_messages<S: AsRef<str>>(messages: &[S]) -> Vec<Self> {
+        messages
+            .iter()
+            .filter_map(|message| Self::from_log_message(message.as_ref()))
+            .collect()
+    }
+}
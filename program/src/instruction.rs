@@ -1,45 +1,170 @@
-use crate::utils::pda_swap;
+use crate::rates::Oracle;
+use crate::state::{ExpiryPolicy, FxFeed, SwapKind};
+use crate::utils::{
+    pda_config, pda_feed_registry, pda_fx_account, pda_liquidity_authority, pda_liquidity_registry,
+    pda_program_data, pda_receipt, pda_vault,
+};
 use borsh::{BorshDeserialize as Deserialize, BorshSerialize as Serialize};
 use rust_decimal::Decimal;
 use solana_program::instruction::{AccountMeta, Instruction};
 use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
 use solana_program::rent::Rent;
+use solana_program::system_program;
 use solana_program::sysvar::SysvarId;
 use std::time::Duration;
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub enum FxEvent {
     /// Request a quoted FX swap between the [`from`] & [`to`] accounts based on the provided limits.
-    /// if either the [`upper_limit`] or [`lower_limit`] is exceeded,
+    /// Once the rate reaches the [`upper_limit`] or [`lower_limit`], as selected by the [`kind`],
     /// the quote is settled at the current market rate.
-    /// If the [`valid_until`] is exceeded, the quote is settled at the current market rate.
+    /// If the [`valid_until`] is exceeded, the quote is handled according to the [`expiry`] policy.
+    /// The [`oracle`] needs to be the one registered for the FX feed.
+    /// The funds are held in escrow by the swap's [`vault`] until it's executed.
+    /// With [`receipt`], executions are recorded in the swap's settlement receipt, whose rent is funded by the [`payer`].
+    /// A receipt is rejected if an earlier swap with the same [`nonce`] already left one.
     /// Accounts:
-    ///     0. [`initializer`] - The account of the person initializing the fx swap
-    ///     1. [`from_account`] `[signer]` `[writable]` Temporary token account that should be created prior to this instruction and owned by the initializer
+    ///     0. [`initializer`] `[signer]` `[writable]` The account of the person initializing the fx swap, receiving the rent of the closed [`from_account`]
+    ///     1. [`from_account`] `[signer]` `[writable]` Temporary token account that should be created prior to this instruction and owned by the [`vault`]
     ///     2. [`to_account`] The receiver's token account for the funds they will receive when the swap executes
-    ///     3. [`fx_account`] `[signer]` `[writable]` The fx account, it will hold all necessary info about the swap.
+    ///     3. [`fx_account`] `[writable]` The fx account created to hold all necessary info about the swap, derived from the [`initializer`] & [`nonce`]
     ///     4. [`rent`] The rent sysvar
     ///     5. [`token`] The SPL token program
-    ///     6. [`fx_feed`] The program providing the FX feed
-    ///     7. [`from_liquidity_account`] `[writable]` The liquidity provider for the [`from_account`]'s token
-    ///     8. [`pda_account`] Program derived address for the [`from_account`] transfer
+    ///     6. [`fx_feed`] The FX feed registered for the [`from_account`] & [`to_account`] tokens, provided by the [`oracle`]
+    ///     7. [`from_liquidity_account`] The liquidity provider for the [`from_account`]'s token, receiving the funds on execution
+    ///     8. [`vault`] `[writable]` The swap's escrow token account, derived from the [`fx_account`]
+    ///     9. [`liquidity_registry`] The registry of liquidity providers
+    ///     10. [`fx_feed_registry`] The registry of FX feeds
+    ///     11. [`from_mint`] The mint of the [`from_account`]'s token
+    ///     12. [`to_mint`] The mint of the [`to_account`]'s token
+    ///     13. [`refund_account`] The initializer's token account of the [`from_mint`], refunded on expiry
+    ///     14. [`payer`] `[signer]` `[writable]` The account funding the rent of the [`fx_account`], [`vault`] & receipt, and the [`crank_reward`]
+    ///     15. [`system_program`] The system program
+    ///     16. [`receipt`] The swap's settlement receipt, derived from the [`fx_account`]
+    ///     17. [`cross_feed`] (optional) The second FX feed, for pairs quoted through the registry's base currency
     Initiate {
         amount: u64,
         upper_limit: Decimal,
         lower_limit: Decimal,
         valid_for: u64,
+        oracle: Oracle,
+        kind: SwapKind,
+        expiry: ExpiryPolicy,
+        nonce: u64,
+        crank_reward: u64,
+        receipt: bool,
     },
-    /// Attempt to settle the FX swap based on the initiated conditions
+    /// Attempt to settle the FX swap based on the initiated conditions.
+    /// Settles at most [`max_amount`] of the remaining amount, in the initiated token, or all of it if omitted
+    /// or paid out through the delegate.
+    /// Fills converting to nothing after the fee & spread are rejected.
+    /// The filled amount is released from the [`vault`] to the [`from_liquidity`].
+    /// The configured protocol fee & spread are deducted from the converted amount.
+    /// The fx account & vault are closed once the swap is fully filled, paying the crank reward to the [`cranker`].
+    /// Swaps initiated with a receipt record the execution in their [`receipt`], created on the first execution.
+    /// Anyone can execute an eligible swap, paid out by a provider which approved its own [`liquidity_authority`] as its delegate.
     /// Accounts:
     ///     0. [`initializer`] - `[writable]` The account of the person initializing the fx swap
     ///     1. [`to_account`] `[writable]` The receiver's token account for the funds they will receive when the swap executes
-    ///     2. [`to_liquidity`] `[signer]` `[writable]` The liquidity provider for the [`to_account`]'s token
+    ///     2. [`to_liquidity`] `[signer]` (unless delegated) `[writable]` The liquidity provider for the [`to_account`]'s token selected on initiate
     ///     3. [`fx_account`] `[writable]` The fx account, it will hold all necessary info about the swap.
     ///     4. [`token`] The SPL token program
-    ///     5. [`fx_feed`] The program providing the FX feed
-    ///     6. [`fx_program`] The Fx-swap program
-    TryExecute,
+    ///     5. [`fx_feed`] The FX feed
+    ///     6. [`fx_program`] The oracle program providing the FX feed
+    ///     7. [`liquidity_registry`] The registry of liquidity providers
+    ///     8. [`from_liquidity`] `[writable]` The liquidity provider for the initiated token
+    ///     9. [`vault`] `[writable]` The swap's escrow token account
+    ///     10. [`config`] The program's fee configuration
+    ///     11. [`fee_account`] `[writable]` The fee collector's token account of the [`to_account`]'s token, unused without a protocol fee
+    ///     12. [`cranker`] `[signer]` `[writable]` The account executing the swap, receiving the crank reward
+    ///     13. [`liquidity_authority`] The delegate of the [`to_liquidity`], derived from it, unused when it signs itself
+    ///     14. [`receipt`] `[writable]` The swap's settlement receipt, derived from the [`fx_account`], unused without a receipt
+    ///     15. [`system_program`] The system program
+    ///     16. [`cross_feed`] (optional) The second FX feed of a cross rate
+    TryExecute { max_amount: Option<u64> },
+    /// Cancel an unexecuted FX swap & refund the unfilled amount to the initializer.
+    /// Only the initializer signs, the funds being held in the swap's vault.
+    /// Accounts:
+    ///     0. [`initializer`] `[signer]` `[writable]` The account of the person initializing the fx swap
+    ///     1. [`refund_account`] `[writable]` The initializer's token account receiving the refunded funds
+    ///     2. [`vault`] `[writable]` The swap's escrow token account
+    ///     3. [`fx_account`] `[writable]` The fx account holding all necessary info about the swap.
+    ///     4. [`token`] The SPL token program
+    Cancel,
+    /// Register a liquidity provider for the token of [`liquidity_account`].
+    /// The registry is created on first use by the program's upgrade authority, which becomes its administrator.
+    /// Accounts:
+    ///     0. [`admin`] `[signer]` `[writable]` The registry administrator, funding the registry on creation
+    ///     1. [`liquidity_registry`] `[writable]` The registry of liquidity providers
+    ///     2. [`liquidity_account`] The liquidity provider's token account
+    ///     3. [`system_program`] The system program
+    ///     4. [`program_data`] The program's ProgramData account, recording its upgrade authority
+    RegisterLiquidity,
+    /// Remove a liquidity provider from the registry
+    /// Accounts:
+    ///     0. [`admin`] `[signer]` The registry administrator
+    ///     1. [`liquidity_registry`] `[writable]` The registry of liquidity providers
+    ///     2. [`liquidity_account`] The liquidity provider's token account
+    DeregisterLiquidity,
+    /// Register the FX feed for a currency pair, read by the feed's [`oracle`].
+    /// The registry is created on first use by the program's upgrade authority, which becomes its administrator.
+    /// Accounts:
+    ///     0. [`admin`] `[signer]` `[writable]` The registry administrator, funding the registry on creation
+    ///     1. [`fx_feed_registry`] `[writable]` The registry of FX feeds
+    ///     2. [`system_program`] The system program
+    ///     3. [`program_data`] The program's ProgramData account, recording its upgrade authority
+    AddFxFeed { feed: FxFeed },
+    /// Replace the FX feed of a registered currency pair
+    /// Accounts:
+    ///     0. [`admin`] `[signer]` The registry administrator
+    ///     1. [`fx_feed_registry`] `[writable]` The registry of FX feeds
+    UpdateFxFeed { feed: FxFeed },
+    /// Remove the FX feed of a registered currency pair
+    /// Accounts:
+    ///     0. [`admin`] `[signer]` The registry administrator
+    ///     1. [`fx_feed_registry`] `[writable]` The registry of FX feeds
+    RemoveFxFeed { from_mint: Pubkey, to_mint: Pubkey },
+    /// Set the currency to derive cross rates through, for pairs without a registered FX feed
+    /// Accounts:
+    ///     0. [`admin`] `[signer]` The registry administrator
+    ///     1. [`fx_feed_registry`] `[writable]` The registry of FX feeds
+    SetBaseMint { base_mint: Option<Pubkey> },
+    /// Upgrade an FX account to the current [`FxData`] layout, reallocating it as needed.
+    /// Swaps of the original program paid their amount to the sending liquidity provider on initiation,
+    /// which escrows it in the swap's vault again.
+    /// Accounts:
+    ///     0. [`payer`] `[signer]` `[writable]` The account funding the rent of the larger FX account & the vault
+    ///     1. [`fx_account`] `[writable]` The fx account to upgrade
+    ///     2. [`system_program`] The system program
+    ///     3. [`from_liquidity`] `[signer]` `[writable]` The liquidity provider paid on initiation
+    ///     4. [`vault`] `[writable]` The swap's escrow token account, not yet created
+    ///     5. [`from_mint`] The mint of the initiated token
+    ///     6. [`token`] The SPL token program
+    ///     7. [`rent`] The rent sysvar
+    Migrate,
+    /// Refund the unfilled amount of an expired FX swap with the [`ExpiryPolicy::Refund`] policy
+    /// to the initializer's source token account. Anyone can submit the refund.
+    /// It's paid out of the swap's own vault, without any authority over the liquidity providers' funds.
+    /// Accounts:
+    ///     0. [`initializer`] `[writable]` The account of the person initializing the fx swap
+    ///     1. [`refund_account`] `[writable]` The initializer's source token account
+    ///     2. [`vault`] `[writable]` The swap's escrow token account
+    ///     3. [`fx_account`] `[writable]` The fx account holding all necessary info about the swap.
+    ///     4. [`token`] The SPL token program
+    Refund,
+    /// Set the protocol fee & liquidity provider spread, in basis points, deducted from executed swaps.
+    /// The config is created on first use by the program's upgrade authority, which becomes its administrator.
+    /// Accounts:
+    ///     0. [`admin`] `[signer]` `[writable]` The config administrator, funding the config on creation
+    ///     1. [`config`] `[writable]` The program's fee configuration
+    ///     2. [`system_program`] The system program
+    ///     3. [`program_data`] The program's ProgramData account, recording its upgrade authority
+    SetFees {
+        fee_bps: u16,
+        spread_bps: u16,
+        fee_collector: Pubkey,
+    },
 }
 
 impl FxEvent {
@@ -52,17 +177,49 @@ impl FxEvent {
 #[allow(clippy::too_many_arguments)]
 pub fn initiate(
     initializer: Pubkey,
+    payer: Pubkey,
     from: Pubkey,
     to: Pubkey,
-    fx_account: Pubkey,
+    refund_account: Pubkey,
+    from_mint: Pubkey,
+    to_mint: Pubkey,
+    nonce: u64,
     fx_feed: Pubkey,
+    cross_feed: Option<Pubkey>,
     from_liquidity: Pubkey,
     amount: u64,
     upper_limit: Decimal,
     lower_limit: Decimal,
     valid_for: Option<Duration>,
+    oracle: Oracle,
+    kind: SwapKind,
+    expiry: ExpiryPolicy,
+    crank_reward: u64,
+    receipt: bool,
 ) -> Instruction {
-    let (pda, _) = pda_swap();
+    let (fx_account, _) = pda_fx_account(&initializer, nonce);
+    let mut accounts = vec![
+        AccountMeta::new(initializer, true),
+        AccountMeta::new(from, true),
+        AccountMeta::new_readonly(to, false),
+        AccountMeta::new(fx_account, false),
+        AccountMeta::new_readonly(Rent::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(fx_feed, false),
+        AccountMeta::new_readonly(from_liquidity, false),
+        AccountMeta::new(pda_vault(&fx_account).0, false),
+        AccountMeta::new_readonly(pda_liquidity_registry().0, false),
+        AccountMeta::new_readonly(pda_feed_registry().0, false),
+        AccountMeta::new_readonly(from_mint, false),
+        AccountMeta::new_readonly(to_mint, false),
+        AccountMeta::new_readonly(refund_account, false),
+        AccountMeta::new(payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(pda_receipt(&fx_account).0, false),
+    ];
+    if let Some(cross_feed) = cross_feed {
+        accounts.push(AccountMeta::new_readonly(cross_feed, false));
+    }
     Instruction::new_with_borsh(
         crate::id(),
         &FxEvent::Initiate {
@@ -72,39 +229,196 @@ pub fn initiate(
             valid_for: valid_for
                 .unwrap_or_else(|| Duration::from_secs(300))
                 .as_secs(),
+            oracle,
+            kind,
+            expiry,
+            nonce,
+            crank_reward,
+            receipt,
         },
-        vec![
-            AccountMeta::new_readonly(initializer, false),
-            AccountMeta::new(from, true),
-            AccountMeta::new_readonly(to, false),
-            AccountMeta::new(fx_account, true),
-            AccountMeta::new_readonly(Rent::id(), false),
-            AccountMeta::new_readonly(spl_token::id(), false),
-            AccountMeta::new_readonly(fx_feed, false),
-            AccountMeta::new(from_liquidity, false),
-            AccountMeta::new_readonly(pda, false),
-        ],
+        accounts,
     )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn execute(
+    cranker: Pubkey,
     initializer: Pubkey,
     to: Pubkey,
     to_liquidity: Pubkey,
+    delegated: bool,
+    from_liquidity: Pubkey,
     fx_account: Pubkey,
     fx_feed: Pubkey,
+    fx_program: Pubkey,
+    cross_feed: Option<Pubkey>,
+    fee_account: Option<Pubkey>,
+    max_amount: Option<u64>,
 ) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(initializer, false),
+        AccountMeta::new(to, false),
+        AccountMeta::new(to_liquidity, !delegated),
+        AccountMeta::new(fx_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(fx_feed, false),
+        AccountMeta::new_readonly(fx_program, false),
+        AccountMeta::new_readonly(pda_liquidity_registry().0, false),
+        AccountMeta::new(from_liquidity, false),
+        AccountMeta::new(pda_vault(&fx_account).0, false),
+        AccountMeta::new_readonly(pda_config().0, false),
+        AccountMeta::new(fee_account.unwrap_or(to), false),
+        AccountMeta::new(cranker, true),
+        AccountMeta::new_readonly(pda_liquidity_authority(&to_liquidity).0, false),
+        AccountMeta::new(pda_receipt(&fx_account).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    if let Some(cross_feed) = cross_feed {
+        accounts.push(AccountMeta::new_readonly(cross_feed, false));
+    }
+    Instruction::new_with_borsh(crate::id(), &FxEvent::TryExecute { max_amount }, accounts)
+}
+
+pub fn cancel(initializer: Pubkey, refund_account: Pubkey, fx_account: Pubkey) -> Instruction {
     Instruction::new_with_borsh(
         crate::id(),
-        &FxEvent::TryExecute,
+        &FxEvent::Cancel,
+        vec![
+            AccountMeta::new(initializer, true),
+            AccountMeta::new(refund_account, false),
+            AccountMeta::new(pda_vault(&fx_account).0, false),
+            AccountMeta::new(fx_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    )
+}
+
+pub fn register_liquidity(admin: Pubkey, liquidity: Pubkey) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &FxEvent::RegisterLiquidity,
+        vec![
+            AccountMeta::new(admin, true),
+            AccountMeta::new(pda_liquidity_registry().0, false),
+            AccountMeta::new_readonly(liquidity, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(pda_program_data().0, false),
+        ],
+    )
+}
+
+pub fn deregister_liquidity(admin: Pubkey, liquidity: Pubkey) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &FxEvent::DeregisterLiquidity,
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(pda_liquidity_registry().0, false),
+            AccountMeta::new_readonly(liquidity, false),
+        ],
+    )
+}
+
+pub fn add_fx_feed(admin: Pubkey, feed: FxFeed) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &FxEvent::AddFxFeed { feed },
+        vec![
+            AccountMeta::new(admin, true),
+            AccountMeta::new(pda_feed_registry().0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(pda_program_data().0, false),
+        ],
+    )
+}
+
+pub fn update_fx_feed(admin: Pubkey, feed: FxFeed) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &FxEvent::UpdateFxFeed { feed },
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(pda_feed_registry().0, false),
+        ],
+    )
+}
+
+pub fn remove_fx_feed(admin: Pubkey, from_mint: Pubkey, to_mint: Pubkey) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &FxEvent::RemoveFxFeed { from_mint, to_mint },
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(pda_feed_registry().0, false),
+        ],
+    )
+}
+
+pub fn set_base_mint(admin: Pubkey, base_mint: Option<Pubkey>) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &FxEvent::SetBaseMint { base_mint },
+        vec![
+            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new(pda_feed_registry().0, false),
+        ],
+    )
+}
+
+pub fn migrate(
+    payer: Pubkey,
+    fx_account: Pubkey,
+    from_liquidity: Pubkey,
+    from_mint: Pubkey,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &FxEvent::Migrate,
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(fx_account, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(from_liquidity, true),
+            AccountMeta::new(pda_vault(&fx_account).0, false),
+            AccountMeta::new_readonly(from_mint, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(Rent::id(), false),
+        ],
+    )
+}
+
+pub fn refund(initializer: Pubkey, refund_account: Pubkey, fx_account: Pubkey) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &FxEvent::Refund,
         vec![
             AccountMeta::new(initializer, false),
-            AccountMeta::new(to, false),
-            AccountMeta::new(to_liquidity, true),
+            AccountMeta::new(refund_account, false),
+            AccountMeta::new(pda_vault(&fx_account).0, false),
             AccountMeta::new(fx_account, false),
             AccountMeta::new_readonly(spl_token::id(), false),
-            AccountMeta::new_readonly(fx_feed, false),
-            AccountMeta::new_readonly(crate::id(), false),
+        ],
+    )
+}
+
+pub fn set_fees(
+    admin: Pubkey,
+    fee_bps: u16,
+    spread_bps: u16,
+    fee_collector: Pubkey,
+) -> Instruction {
+    Instruction::new_with_borsh(
+        crate::id(),
+        &FxEvent::SetFees {
+            fee_bps,
+            spread_bps,
+            fee_collector,
+        },
+        vec![
+            AccountMeta::new(admin, true),
+            AccountMeta::new(pda_config().0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(pda_program_data().0, false),
         ],
     )
 }
@@ -1,5 +1,7 @@
+pub mod accounts;
 pub mod entrypoint;
 pub mod error;
+pub mod events;
 pub mod instruction;
 pub mod liquidity;
 pub mod processor;
@@ -1,9 +1,22 @@
+use crate::accounts::{
+    check_admin, check_upgrade_authority, CloseAccounts, InitiateAccounts, TryExecuteAccounts,
+};
 use crate::error::FxError;
+use crate::events::{
+    Event, SwapCancelled, SwapConditionsNotMet, SwapExecuted, SwapInitiated, SwapRefunded,
+};
 use crate::instruction::FxEvent;
-use crate::liquidity::{DemoLiquidity, LiquidityProvider};
-use crate::rates::{DemoFx, FxRates};
-use crate::state::FxData;
-use crate::utils::{pda_swap, PDA_SEED};
+use crate::liquidity::LiquidityProvider;
+use crate::rates::{convert_amount, feed_for_token, CrossRateFx, Oracle};
+use crate::state::{
+    Administered, ExpiryPolicy, FxConfig, FxData, FxDataV0, FxFeed, FxFeedRegistry, LiquidityEntry,
+    LiquidityRegistry, SettlementReceipt, SwapKind, BPS, MAX_FX_FEEDS, MAX_LIQUIDITY_PROVIDERS,
+};
+use crate::utils::{
+    close_program_account, pda_config, pda_feed_registry, pda_liquidity_authority,
+    pda_liquidity_registry, pda_vault, CONFIG_SEED, FEED_REGISTRY_SEED, FX_ACCOUNT_SEED,
+    LIQUIDITY_AUTHORITY_SEED, LIQUIDITY_REGISTRY_SEED, RECEIPT_SEED, VAULT_SEED,
+};
 use rust_decimal::Decimal;
 use solana_program::account_info::{next_account_info, AccountInfo};
 use solana_program::clock::Clock;
@@ -14,8 +27,9 @@ use solana_program::program_error::ProgramError;
 use solana_program::program_pack::{IsInitialized, Pack};
 use solana_program::pubkey::Pubkey;
 use solana_program::rent::Rent;
+use solana_program::system_instruction;
 use solana_program::sysvar::Sysvar;
-use spl_token::state::Account;
+use spl_token::state::{Account, Mint};
 use std::ops::Range;
 use std::time::Duration;
 
@@ -23,7 +37,7 @@ pub struct FxSwap;
 
 impl FxSwap {
     pub fn process(
-        _program_id: &Pubkey,
+        program_id: &Pubkey,
         accounts: &[AccountInfo],
         instruction_data: &[u8],
     ) -> ProgramResult {
@@ -33,84 +47,240 @@ impl FxSwap {
                 upper_limit,
                 lower_limit,
                 valid_for,
+                oracle,
+                kind,
+                expiry,
+                nonce,
+                crank_reward,
+                receipt,
             } => {
                 // Validate parameters
                 if lower_limit > upper_limit {
                     return Err(FxError::InvalidRequest)?;
                 }
+                if amount > i64::MAX as u64 {
+                    return Err(FxError::InvalidAmount)?;
+                }
 
                 let limits = lower_limit..upper_limit;
                 msg!(
-                    "Initiate amount={} limit={:?} valid_until={:?}",
+                    "Initiate amount={} limit={:?} valid_until={:?} oracle={:?} kind={:?} expiry={:?} nonce={} crank_reward={} receipt={}",
                     amount,
                     limits,
                     valid_for,
+                    oracle,
+                    kind,
+                    expiry,
+                    nonce,
+                    crank_reward,
+                    receipt,
                 );
-                Self::initiate(accounts, amount, limits, Duration::from_secs(valid_for))
+                Self::initiate(
+                    program_id,
+                    accounts,
+                    amount,
+                    limits,
+                    Duration::from_secs(valid_for),
+                    oracle,
+                    kind,
+                    expiry,
+                    nonce,
+                    crank_reward,
+                    receipt,
+                )
+            }
+            FxEvent::TryExecute { max_amount } => {
+                msg!("Trying to execute max_amount={:?}", max_amount);
+                Self::try_execute(program_id, accounts, max_amount)
+            }
+            FxEvent::Cancel => {
+                msg!("Cancelling");
+                Self::cancel(program_id, accounts)
+            }
+            FxEvent::RegisterLiquidity => {
+                msg!("Registering liquidity");
+                Self::register_liquidity(program_id, accounts)
+            }
+            FxEvent::DeregisterLiquidity => {
+                msg!("Deregistering liquidity");
+                Self::deregister_liquidity(program_id, accounts)
+            }
+            FxEvent::AddFxFeed { feed } => {
+                msg!("Adding FX feed {:?}", feed);
+                Self::add_fx_feed(program_id, accounts, feed)
+            }
+            FxEvent::UpdateFxFeed { feed } => {
+                msg!("Updating FX feed {:?}", feed);
+                Self::update_fx_feed(program_id, accounts, feed)
+            }
+            FxEvent::RemoveFxFeed { from_mint, to_mint } => {
+                msg!("Removing FX feed {} -> {}", from_mint, to_mint);
+                Self::remove_fx_feed(program_id, accounts, &from_mint, &to_mint)
+            }
+            FxEvent::SetBaseMint { base_mint } => {
+                msg!("Setting base mint {:?}", base_mint);
+                Self::set_base_mint(program_id, accounts, base_mint)
             }
-            FxEvent::TryExecute => {
-                msg!("Trying to execute");
-                Self::try_execute(accounts)
+            FxEvent::Migrate => {
+                msg!("Migrating FX account");
+                Self::migrate(program_id, accounts)
+            }
+            FxEvent::Refund => {
+                msg!("Refunding");
+                Self::refund(program_id, accounts)
+            }
+            FxEvent::SetFees {
+                fee_bps,
+                spread_bps,
+                fee_collector,
+            } => {
+                msg!(
+                    "Setting fee_bps={} spread_bps={} fee_collector={}",
+                    fee_bps,
+                    spread_bps,
+                    fee_collector
+                );
+                Self::set_fees(program_id, accounts, fee_bps, spread_bps, fee_collector)
             }
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn initiate(
+        program_id: &Pubkey,
         accounts: &[AccountInfo],
         amount: u64,
         limits: Range<Decimal>,
         valid_for: Duration,
+        oracle: Oracle,
+        kind: SwapKind,
+        expiry: ExpiryPolicy,
+        nonce: u64,
+        crank_reward: u64,
+        receipt: bool,
     ) -> ProgramResult {
-        let account_info_iter = &mut accounts.iter();
+        let InitiateAccounts {
+            initializer,
+            from_account,
+            to_account,
+            fx_account,
+            rent_sysvar,
+            token,
+            fx_feed,
+            from_liquidity: from_liquidity_account,
+            vault,
+            liquidity_registry,
+            fx_feed_registry,
+            from_mint,
+            to_mint,
+            refund_account,
+            payer,
+            system_program,
+            receipt: receipt_account,
+            cross_feed,
+            fx_bump_seed,
+            vault_bump_seed,
+        } = InitiateAccounts::validate(accounts, nonce)?;
 
-        // Extract accounts
-        let initializer = next_account_info(account_info_iter)?;
-        let from_account = next_account_info(account_info_iter)?;
-        let to_account = next_account_info(account_info_iter)?;
-        let fx_account = next_account_info(account_info_iter)?;
-        let rent = next_account_info(account_info_iter)?;
-        let token = next_account_info(account_info_iter)?;
-        let fx_feed = next_account_info(account_info_iter)?;
-        let from_liquidity_account = next_account_info(account_info_iter)?;
-        let pda_account = next_account_info(account_info_iter)?;
-
-        // Generate PDA
-        let (pda, bump_seed) = pda_swap();
+        if !fx_account.data_is_empty() {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        // A receipt only records a single swap, so a reused nonce can't add to the receipt of an earlier swap
+        if receipt && !receipt_account.data_is_empty() {
+            return Err(FxError::InvalidReceipt)?;
+        }
+        let vault_seeds: &[&[u8]] = &[VAULT_SEED, fx_account.key.as_ref(), &[vault_bump_seed]];
 
         // The from & to holding accounts need to be part of a swappable token
         spl_token::check_program_account(from_account.owner)
             .map_err(|_| FxError::InvalidTokenId)?;
         spl_token::check_program_account(to_account.owner).map_err(|_| FxError::InvalidTokenId)?;
 
-        // Validate the account is rent-exempt
-        let rent = &Rent::from_account_info(rent).map_err(|_| FxError::NotRentExempt)?;
-        if !rent.is_exempt(fx_account.lamports(), fx_account.data_len()) {
-            return Err(FxError::NotRentExempt)?;
-        }
+        let rent = &Rent::from_account_info(rent_sysvar).map_err(|_| FxError::NotRentExempt)?;
 
         // Check ephemeral `from` account balance
-        if Account::unpack(&from_account.try_borrow_data()?)?.amount != amount {
+        let from_token = Account::unpack(&from_account.try_borrow_data()?)?;
+        if from_token.amount != amount {
             return Err(FxError::InvalidAmount)?;
         }
+        let to_token = Account::unpack(&to_account.try_borrow_data()?)?;
+
+        // Record the decimals of both tokens to convert between their base units
+        if from_token.mint != *from_mint.key || to_token.mint != *to_mint.key {
+            return Err(FxError::MintMismatch)?;
+        }
+        spl_token::check_program_account(from_mint.owner).map_err(|_| FxError::InvalidTokenId)?;
+        spl_token::check_program_account(to_mint.owner).map_err(|_| FxError::InvalidTokenId)?;
+        let from_decimals = Mint::unpack(&from_mint.try_borrow_data()?)?.decimals;
+        let to_decimals = Mint::unpack(&to_mint.try_borrow_data()?)?.decimals;
+
+        // Expired swaps are refunded in the original token
+        spl_token::check_program_account(refund_account.owner)
+            .map_err(|_| FxError::InvalidTokenId)?;
+        if Account::unpack(&refund_account.try_borrow_data()?)?.mint != from_token.mint {
+            return Err(FxError::InvalidTokenId)?;
+        }
 
         // Retrieve the liquidity providers
-        let from_liquidity =
-            DemoLiquidity::liquidity_account(&Account::unpack(&from_account.try_borrow_data()?)?)
-                .ok_or(FxError::NoLiquidity)?;
-        if from_liquidity != *from_liquidity_account.key {
+        let registry = Self::liquidity_registry(program_id, liquidity_registry)?;
+        if !registry.provides(&from_token, from_liquidity_account.key) {
             return Err(FxError::InvalidRequest)?;
         }
-        let to_liquidity =
-            DemoLiquidity::liquidity_account(&Account::unpack(&to_account.try_borrow_data()?)?)
-                .ok_or(FxError::NoLiquidity)?;
+        let from_liquidity = *from_liquidity_account.key;
+        let to_liquidity = registry
+            .liquidity_account(&to_token)
+            .ok_or(FxError::NoLiquidity)?;
+
+        // The FX feed needs to be registered for the swapped tokens
+        let feeds = Self::fx_feed_registry(program_id, fx_feed_registry)?;
+        let route = feed_for_token(&feeds, &from_token.mint, &to_token.mint)
+            .ok_or(FxError::InvalidFxFeed)?;
+        let feed = route.feed();
+        if feed.feed != *fx_feed.key
+            || route.cross_feed().map(|feed| feed.feed) != cross_feed.map(|feed| *feed.key)
+        {
+            return Err(FxError::InvalidFxFeed)?;
+        }
+        // Any other oracle can't read the feed, leaving the swap to expire
+        if oracle != route.oracle() {
+            return Err(FxError::InvalidFxFeed)?;
+        }
+
+        // Create the FX account
+        Self::create_program_account(
+            program_id,
+            payer,
+            fx_account,
+            system_program,
+            FxData::LEN,
+            &[
+                FX_ACCOUNT_SEED,
+                initializer.key.as_ref(),
+                &nonce.to_le_bytes(),
+                &[fx_bump_seed],
+            ],
+        )?;
+
+        // Fund the reward of whoever executes the swap, and the rent of its receipt
+        let receipt_rent = if receipt {
+            rent.minimum_balance(SettlementReceipt::LEN)
+        } else {
+            0
+        };
+        let deposit = crank_reward
+            .checked_add(receipt_rent)
+            .ok_or(FxError::InvalidAmount)?;
+        if deposit > 0 {
+            let fund_reward_ix = system_instruction::transfer(payer.key, fx_account.key, deposit);
+            invoke(
+                &fund_reward_ix,
+                &[payer.clone(), fx_account.clone(), system_program.clone()],
+            )?;
+        }
 
         // Initialize the FX data in the account
         let valid_until = Clock::get()?.unix_timestamp + valid_for.as_secs() as i64;
-        let mut fx_data = FxData::unpack_unchecked(&fx_account.try_borrow_data()?)?;
-        if fx_data.is_initialized() {
-            return Err(ProgramError::AccountAlreadyInitialized);
-        }
-        fx_data = FxData {
+        let fx_data = FxData {
             is_initialized: true,
             initializer: *initializer.key,
             from_holding: *from_account.key,
@@ -118,29 +288,79 @@ impl FxSwap {
             from_liquidity,
             to_liquidity,
             amount,
+            amount_filled: 0,
+            from_decimals,
+            to_decimals,
             limits,
             valid_until,
+            kind,
+            expiry,
+            refund_account: *refund_account.key,
+            crank_reward,
+            receipt,
+            oracle,
             fx_feed: *fx_feed.key,
+            fx_feed_inverted: feed.inverted,
+            oracle_program: feed.oracle_program,
+            max_rate_age: route.max_age(),
+            cross_feed: route.cross_feed().map(|feed| feed.feed),
+            cross_feed_inverted: route
+                .cross_feed()
+                .map(|feed| feed.inverted)
+                .unwrap_or_default(),
+        };
+        let initiated = SwapInitiated {
+            fx_account: *fx_account.key,
+            initializer: fx_data.initializer,
+            from_holding: fx_data.from_holding,
+            to_holding: fx_data.to_holding,
+            amount,
+            lower_limit: fx_data.limits.start,
+            upper_limit: fx_data.limits.end,
+            valid_until,
+            kind,
+            expiry,
         };
         FxData::pack(fx_data, &mut fx_account.try_borrow_mut_data()?)?;
 
-        // Transfer the funds from `from` -> `liquidity`
+        // Create the vault, acting as its own authority
+        Self::create_pda_account(
+            payer,
+            vault,
+            system_program,
+            Account::LEN,
+            token.key,
+            vault_seeds,
+        )?;
+        let init_vault_ix = spl_token::instruction::initialize_account(
+            token.key,
+            vault.key,
+            from_mint.key,
+            vault.key,
+        )?;
+        invoke(
+            &init_vault_ix,
+            &[
+                vault.clone(),
+                from_mint.clone(),
+                vault.clone(),
+                rent_sysvar.clone(),
+            ],
+        )?;
+
+        // Transfer the funds from `from` -> `vault`
         let transfer_funds_ix = spl_token::instruction::transfer(
             token.key,
             from_account.key,
-            from_liquidity_account.key,
-            &pda,
-            &[&pda],
+            vault.key,
+            vault.key,
+            &[vault.key],
             amount,
         )?;
         invoke_signed(
             &transfer_funds_ix,
-            &[
-                from_account.clone(),
-                from_liquidity_account.clone(),
-                pda_account.clone(),
-            ],
-            &[&[PDA_SEED, &[bump_seed]]],
+            &[from_account.clone(), vault.clone(), vault.clone()],
+            &[vault_seeds],
         )?;
 
         // Close the `from` account
@@ -148,44 +368,71 @@ impl FxSwap {
             token.key,
             from_account.key,
             initializer.key,
-            &pda,
-            &[&pda],
+            vault.key,
+            &[vault.key],
         )?;
         invoke_signed(
             &close_account_ix,
-            &[
-                from_account.clone(),
-                initializer.clone(),
-                pda_account.clone(),
-            ],
-            &[&[PDA_SEED, &[bump_seed]]],
+            &[from_account.clone(), initializer.clone(), vault.clone()],
+            &[vault_seeds],
         )?;
 
+        initiated.emit();
         Ok(())
     }
 
-    fn try_execute(accounts: &[AccountInfo]) -> ProgramResult {
-        let account_info_iter = &mut accounts.iter();
-        let initializer = next_account_info(account_info_iter)?;
-        let to_account = next_account_info(account_info_iter)?;
-        let to_liquidity = next_account_info(account_info_iter)?;
-        let fx_account = next_account_info(account_info_iter)?;
-        let token = next_account_info(account_info_iter)?;
-        let fx_feed = next_account_info(account_info_iter)?;
-        let fx_program = next_account_info(account_info_iter)?;
+    fn try_execute(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        max_amount: Option<u64>,
+    ) -> ProgramResult {
+        let TryExecuteAccounts {
+            initializer,
+            to_account,
+            to_liquidity,
+            fx_account,
+            token,
+            fx_feed,
+            fx_program,
+            liquidity_registry,
+            from_liquidity,
+            vault,
+            config: config_account,
+            fee_account,
+            cranker,
+            liquidity_authority,
+            receipt,
+            system_program,
+            cross_feed,
+            receipt_bump_seed,
+        } = TryExecuteAccounts::validate(program_id, accounts)?;
 
-        let fx_data = FxData::unpack_unchecked(&fx_account.try_borrow_data()?)?;
+        let mut fx_data = FxData::unpack_unchecked(&fx_account.try_borrow_data()?)?;
         // We're trying to execute an uninitialized FX swap
         if !fx_data.is_initialized() {
             return Err(FxError::InvalidRequest)?;
         }
-        // Check if we're executing the swap between the correct accounts
-        if *to_account.key != fx_data.to_holding || *to_liquidity.key != fx_data.to_liquidity {
+        // Check if we're executing the swap towards the correct account
+        if *to_account.key != fx_data.to_holding {
+            return Err(FxError::InvalidRequest)?;
+        }
+
+        // The provider selected on initiate fills the swap, as it receives the escrowed funds
+        // through the matching [`from_liquidity`], as long as it's still registered
+        let to_token = Account::unpack(&to_account.try_borrow_data()?)?;
+        let registry = Self::liquidity_registry(program_id, liquidity_registry)?;
+        if !registry.provides(&to_token, to_liquidity.key) {
+            return Err(FxError::NoLiquidity)?;
+        }
+        if *to_liquidity.key != fx_data.to_liquidity {
             return Err(FxError::InvalidRequest)?;
         }
 
         // The execute is scheduled with a different FX feed
-        if fx_data.fx_feed != *fx_feed.key {
+        if fx_data.fx_feed != *fx_feed.key
+            || fx_data.oracle_program != *fx_program.key
+            || fx_data.cross_feed != cross_feed.map(|feed| *feed.key)
+        {
             return Err(FxError::InvalidFxFeed)?;
         }
 
@@ -194,50 +441,870 @@ impl FxSwap {
             return Err(FxError::InvalidTokenId)?;
         }
 
+        // The escrowed funds are released to the liquidity provider chosen on initiate
+        if fx_data.from_liquidity != *from_liquidity.key {
+            return Err(FxError::InvalidRequest)?;
+        }
+
         // Fetch the current time estimate
         let now = Clock::get()?.unix_timestamp;
 
         // Fetch the current exchange rate
-        let rate = DemoFx::rate(fx_program, fx_feed)?;
+        let quote = match cross_feed {
+            Some(cross_feed) => CrossRateFx::rate(
+                &fx_data.oracle,
+                fx_program,
+                (fx_feed, fx_data.fx_feed_inverted),
+                (cross_feed, fx_data.cross_feed_inverted),
+            )?,
+            None => fx_data
+                .oracle
+                .rate(fx_program, fx_feed)?
+                .directed(fx_data.fx_feed_inverted)?,
+        };
+        let reason = match fx_data.check_execution(&quote, now) {
+            Err(FxError::SwapConditionsNotMet) => {
+                SwapConditionsNotMet {
+                    fx_account: *fx_account.key,
+                    rate: quote.rate,
+                    lower_limit: fx_data.limits.start,
+                    upper_limit: fx_data.limits.end,
+                    valid_until: fx_data.valid_until,
+                    now,
+                }
+                .emit();
+                return Err(FxError::SwapConditionsNotMet)?;
+            }
+            reason => reason?,
+        };
+        let rate = quote.rate;
 
-        // Calculate the swap value
-        let dec = Decimal::new(fx_data.amount as i64, 0);
-        let fx_amount: u64 = (dec * rate)
-            .try_into()
-            .map_err(|_| FxError::InvalidAmount)?;
+        // Fill (part of) the remaining amount. Only the provider itself chooses to fill part of it,
+        // so executions through its delegate can't split the swap into fills rounding down to nothing
+        let remaining = fx_data.amount_remaining();
+        let fill = match max_amount {
+            Some(max_amount) if to_liquidity.is_signer => max_amount.min(remaining),
+            _ => remaining,
+        };
+        if fill == 0 {
+            return Err(FxError::InvalidAmount)?;
+        }
 
-        let in_time = fx_data.valid_until > now;
-        let within_limits = fx_data.limits.contains(&rate);
-        if in_time && within_limits {
-            return Err(FxError::SwapConditionsNotMet)?;
+        // Calculate the swap value, net of the protocol fee & spread
+        let fx_amount = convert_amount(fill, rate, fx_data.from_decimals, fx_data.to_decimals)?;
+        let config = Self::config(program_id, config_account)?;
+        let charges = config.charges(fx_amount);
+        // Fills rounding down to nothing would release the escrowed funds for free
+        if charges.net == 0 {
+            return Err(FxError::InvalidAmount)?;
+        }
+        msg!(
+            "Filling {} of {} at {}: {} (fee {}, spread {})",
+            fill,
+            remaining,
+            rate,
+            charges.net,
+            charges.fee,
+            charges.spread
+        );
+
+        // Transfer [`to_liquidity`] -> [`fee_account`]
+        if charges.fee > 0 {
+            let fee_token = Account::unpack(&fee_account.try_borrow_data()?)?;
+            if fee_token.owner != config.fee_collector || fee_token.mint != to_token.mint {
+                return Err(FxError::InvalidRequest)?;
+            }
+            Self::pay_out(
+                token,
+                to_liquidity,
+                liquidity_authority,
+                fee_account,
+                charges.fee,
+            )?;
         }
 
         // Transfer [`to_liquidity`] -> [`to_account`]
-        let to_swap = spl_token::instruction::transfer(
+        Self::pay_out(
+            token,
+            to_liquidity,
+            liquidity_authority,
+            to_account,
+            charges.net,
+        )?;
+
+        // Release [`vault`] -> [`from_liquidity`]
+        Self::release_vault(token, vault, fx_account, from_liquidity, fill)?;
+
+        fx_data.amount_filled += fill;
+        SwapExecuted {
+            fx_account: *fx_account.key,
+            to_holding: fx_data.to_holding,
+            rate,
+            round_id: quote.round_id,
+            cross_round_id: quote.cross_round_id,
+            amount_in: fill,
+            amount_out: charges.net,
+            fee: charges.fee,
+            spread: charges.spread,
+            amount_filled: fx_data.amount_filled,
+            reason,
+        }
+        .emit();
+
+        // The vault of a fully filled swap is closed before moving any lamports of the FX account
+        let filled = fx_data.amount_remaining() == 0;
+        if filled {
+            Self::close_vault(token, vault, fx_account, initializer)?;
+        }
+        if fx_data.receipt {
+            let execution = SettlementReceipt {
+                is_initialized: true,
+                initializer: fx_data.initializer,
+                from_holding: fx_data.from_holding,
+                to_holding: fx_data.to_holding,
+                amount_in: fill,
+                amount_out: charges.net,
+                rate,
+                round_id: quote.round_id,
+                cross_round_id: quote.cross_round_id,
+                reason,
+                timestamp: now,
+            };
+            Self::record_receipt(
+                program_id,
+                fx_account,
+                receipt,
+                system_program,
+                &[RECEIPT_SEED, fx_account.key.as_ref(), &[receipt_bump_seed]],
+                execution,
+            )?;
+        }
+        if !filled {
+            FxData::pack(fx_data, &mut fx_account.try_borrow_mut_data()?)?;
+            return Ok(());
+        }
+
+        // Reward the cranker & close the fully filled FX account
+        let reward = fx_data.crank_reward.min(fx_account.lamports());
+        **cranker.lamports.borrow_mut() = cranker
+            .lamports()
+            .checked_add(reward)
+            .ok_or(FxError::InvalidAmount)?;
+        **fx_account.lamports.borrow_mut() -= reward;
+        close_program_account(fx_account, initializer)?;
+
+        Ok(())
+    }
+
+    fn cancel(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let CloseAccounts {
+            initializer,
+            refund_account,
+            vault,
+            fx_account,
+            token,
+        } = CloseAccounts::validate(program_id, accounts)?;
+
+        let fx_data = FxData::unpack_unchecked(&fx_account.try_borrow_data()?)?;
+        // We're trying to cancel an uninitialized FX swap
+        if !fx_data.is_initialized() {
+            return Err(FxError::InvalidRequest)?;
+        }
+
+        // Only the initiater can cancel the swap
+        if fx_data.initializer != *initializer.key {
+            return Err(FxError::InvalidTokenId)?;
+        }
+        if !initializer.is_signer {
+            return Err(FxError::MissingSignature)?;
+        }
+
+        // The refund needs to be made in the original token
+        let refund_mint = Account::unpack(&refund_account.try_borrow_data()?)?.mint;
+        let vault_mint = Account::unpack(&vault.try_borrow_data()?)?.mint;
+        if refund_mint != vault_mint {
+            return Err(FxError::InvalidTokenId)?;
+        }
+
+        // Transfer [`vault`] -> [`refund_account`]
+        Self::release_vault(
+            token,
+            vault,
+            fx_account,
+            refund_account,
+            fx_data.amount_remaining(),
+        )?;
+
+        // Close the FX account & its vault
+        Self::close_vault(token, vault, fx_account, initializer)?;
+        close_program_account(fx_account, initializer)?;
+
+        SwapCancelled {
+            fx_account: *fx_account.key,
+            initializer: *initializer.key,
+            refund_account: *refund_account.key,
+            amount_refunded: fx_data.amount_remaining(),
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    fn refund(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let CloseAccounts {
+            initializer,
+            refund_account,
+            vault,
+            fx_account,
+            token,
+        } = CloseAccounts::validate(program_id, accounts)?;
+
+        let fx_data = FxData::unpack_unchecked(&fx_account.try_borrow_data()?)?;
+        // We're trying to refund an uninitialized FX swap
+        if !fx_data.is_initialized() {
+            return Err(FxError::InvalidRequest)?;
+        }
+
+        // Only expired swaps opting for a refund are refunded
+        if fx_data.expiry != ExpiryPolicy::Refund {
+            return Err(FxError::InvalidRequest)?;
+        }
+        if fx_data.valid_until > Clock::get()?.unix_timestamp {
+            return Err(FxError::SwapConditionsNotMet)?;
+        }
+
+        // The funds are refunded to the initializer's source account
+        if fx_data.initializer != *initializer.key || fx_data.refund_account != *refund_account.key
+        {
+            return Err(FxError::InvalidRequest)?;
+        }
+
+        // Transfer [`vault`] -> [`refund_account`]
+        Self::release_vault(
+            token,
+            vault,
+            fx_account,
+            refund_account,
+            fx_data.amount_remaining(),
+        )?;
+
+        // Close the FX account & its vault
+        Self::close_vault(token, vault, fx_account, initializer)?;
+        close_program_account(fx_account, initializer)?;
+
+        SwapRefunded {
+            fx_account: *fx_account.key,
+            initializer: *initializer.key,
+            refund_account: *refund_account.key,
+            amount_refunded: fx_data.amount_remaining(),
+            valid_until: fx_data.valid_until,
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    /// Transfer [`amount`] from the [`liquidity`] provider, either signing itself or through its delegate
+    fn pay_out<'a>(
+        token: &AccountInfo<'a>,
+        liquidity: &AccountInfo<'a>,
+        liquidity_authority: &AccountInfo<'a>,
+        destination: &AccountInfo<'a>,
+        amount: u64,
+    ) -> ProgramResult {
+        if liquidity.is_signer {
+            let pay_out_ix = spl_token::instruction::transfer(
+                token.key,
+                liquidity.key,
+                destination.key,
+                liquidity.key,
+                &[liquidity.key],
+                amount,
+            )?;
+            return invoke(
+                &pay_out_ix,
+                &[liquidity.clone(), destination.clone(), liquidity.clone()],
+            );
+        }
+
+        let (authority, bump_seed) = pda_liquidity_authority(liquidity.key);
+        if *liquidity_authority.key != authority {
+            return Err(FxError::InvalidRequest)?;
+        }
+        let pay_out_ix = spl_token::instruction::transfer(
+            token.key,
+            liquidity.key,
+            destination.key,
+            &authority,
+            &[&authority],
+            amount,
+        )?;
+        invoke_signed(
+            &pay_out_ix,
+            &[
+                liquidity.clone(),
+                destination.clone(),
+                liquidity_authority.clone(),
+            ],
+            &[&[
+                LIQUIDITY_AUTHORITY_SEED,
+                liquidity.key.as_ref(),
+                &[bump_seed],
+            ]],
+        )
+    }
+
+    /// Transfer [`amount`] out of the escrow [`vault`] of the swap held in [`fx_account`]
+    fn release_vault<'a>(
+        token: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        fx_account: &AccountInfo<'a>,
+        destination: &AccountInfo<'a>,
+        amount: u64,
+    ) -> ProgramResult {
+        let (vault_key, bump_seed) = pda_vault(fx_account.key);
+        if *vault.key != vault_key {
+            return Err(FxError::InvalidVault)?;
+        }
+        let release_ix = spl_token::instruction::transfer(
+            token.key,
+            vault.key,
+            destination.key,
+            vault.key,
+            &[vault.key],
+            amount,
+        )?;
+        invoke_signed(
+            &release_ix,
+            &[vault.clone(), destination.clone(), vault.clone()],
+            &[&[VAULT_SEED, fx_account.key.as_ref(), &[bump_seed]]],
+        )
+    }
+
+    /// Add the [`execution`] to the [`receipt`] of the swap held in [`fx_account`].
+    /// The receipt is created on the first execution, paid from the rent deposited in the [`fx_account`].
+    fn record_receipt<'a>(
+        program_id: &Pubkey,
+        fx_account: &AccountInfo<'a>,
+        receipt: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        seeds: &[&[u8]],
+        execution: SettlementReceipt,
+    ) -> ProgramResult {
+        if receipt.data_is_empty() {
+            let allocate_ix =
+                system_instruction::allocate(receipt.key, SettlementReceipt::LEN as u64);
+            invoke_signed(
+                &allocate_ix,
+                &[receipt.clone(), system_program.clone()],
+                &[seeds],
+            )?;
+            let assign_ix = system_instruction::assign(receipt.key, program_id);
+            invoke_signed(
+                &assign_ix,
+                &[receipt.clone(), system_program.clone()],
+                &[seeds],
+            )?;
+
+            // Lamports only move once the system program is done with the receipt
+            let lamports = Rent::get()?
+                .minimum_balance(SettlementReceipt::LEN)
+                .saturating_sub(receipt.lamports());
+            **fx_account.lamports.borrow_mut() = fx_account
+                .lamports()
+                .checked_sub(lamports)
+                .ok_or(FxError::NotRentExempt)?;
+            **receipt.lamports.borrow_mut() = receipt
+                .lamports()
+                .checked_add(lamports)
+                .ok_or(FxError::InvalidAmount)?;
+        } else if receipt.owner != program_id {
+            return Err(FxError::InvalidReceipt)?;
+        }
+
+        // Amounts add up over partial fills, the rest describes the latest execution
+        let recorded = SettlementReceipt::unpack_unchecked(&receipt.try_borrow_data()?)?;
+        let updated = if recorded.is_initialized {
+            SettlementReceipt {
+                amount_in: recorded.amount_in + execution.amount_in,
+                amount_out: recorded.amount_out + execution.amount_out,
+                ..execution
+            }
+        } else {
+            execution
+        };
+        SettlementReceipt::pack(updated, &mut receipt.try_borrow_mut_data()?)
+    }
+
+    /// Close the emptied escrow [`vault`] of the swap held in [`fx_account`], returning its rent to the [`initializer`]
+    fn close_vault<'a>(
+        token: &AccountInfo<'a>,
+        vault: &AccountInfo<'a>,
+        fx_account: &AccountInfo<'a>,
+        initializer: &AccountInfo<'a>,
+    ) -> ProgramResult {
+        let (vault_key, bump_seed) = pda_vault(fx_account.key);
+        if *vault.key != vault_key {
+            return Err(FxError::InvalidVault)?;
+        }
+        let close_ix = spl_token::instruction::close_account(
             token.key,
-            to_liquidity.key,
-            to_account.key,
-            to_liquidity.key,
-            &[to_liquidity.key],
-            fx_amount as u64,
+            vault.key,
+            initializer.key,
+            vault.key,
+            &[vault.key],
+        )?;
+        invoke_signed(
+            &close_ix,
+            &[vault.clone(), initializer.clone(), vault.clone()],
+            &[&[VAULT_SEED, fx_account.key.as_ref(), &[bump_seed]]],
+        )
+    }
+
+    fn set_fees(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        fee_bps: u16,
+        spread_bps: u16,
+        fee_collector: Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin = next_account_info(account_info_iter)?;
+        let config_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let program_data = next_account_info(account_info_iter)?;
+
+        // The charges can't exceed the converted amount
+        if fee_bps as u32 + spread_bps as u32 > BPS as u32 {
+            return Err(FxError::InvalidRequest)?;
+        }
+
+        let (config_key, bump_seed) = pda_config();
+        if *config_account.key != config_key {
+            return Err(FxError::InvalidRequest)?;
+        }
+
+        let config: FxConfig = Self::administered(
+            program_id,
+            admin,
+            config_account,
+            system_program,
+            program_data,
+            &[CONFIG_SEED, &[bump_seed]],
+            Self::config,
+        )?;
+        let config = FxConfig {
+            fee_bps,
+            spread_bps,
+            fee_collector,
+            ..config
+        };
+        FxConfig::pack(config, &mut config_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn register_liquidity(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin = next_account_info(account_info_iter)?;
+        let registry_account = next_account_info(account_info_iter)?;
+        let liquidity_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let program_data = next_account_info(account_info_iter)?;
+
+        // The liquidity account needs to be part of a swappable token
+        spl_token::check_program_account(liquidity_account.owner)
+            .map_err(|_| FxError::InvalidTokenId)?;
+        let mint = Account::unpack(&liquidity_account.try_borrow_data()?)?.mint;
+
+        let (registry_key, bump_seed) = pda_liquidity_registry();
+        if *registry_account.key != registry_key {
+            return Err(FxError::InvalidRequest)?;
+        }
+
+        let mut registry: LiquidityRegistry = Self::administered(
+            program_id,
+            admin,
+            registry_account,
+            system_program,
+            program_data,
+            &[LIQUIDITY_REGISTRY_SEED, &[bump_seed]],
+            Self::liquidity_registry,
+        )?;
+
+        let entry = LiquidityEntry {
+            mint,
+            liquidity: *liquidity_account.key,
+        };
+        if registry.providers.contains(&entry) {
+            return Err(FxError::InvalidRequest)?;
+        }
+        if registry.providers.len() >= MAX_LIQUIDITY_PROVIDERS {
+            return Err(FxError::RegistryFull)?;
+        }
+        registry.providers.push(entry);
+        LiquidityRegistry::pack(registry, &mut registry_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn deregister_liquidity(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin = next_account_info(account_info_iter)?;
+        let registry_account = next_account_info(account_info_iter)?;
+        let liquidity_account = next_account_info(account_info_iter)?;
+
+        let mut registry = Self::liquidity_registry(program_id, registry_account)?;
+        check_admin(admin, &registry.admin)?;
+
+        let position = registry
+            .providers
+            .iter()
+            .position(|entry| entry.liquidity == *liquidity_account.key)
+            .ok_or(FxError::NoLiquidity)?;
+        registry.providers.remove(position);
+        LiquidityRegistry::pack(registry, &mut registry_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn add_fx_feed(program_id: &Pubkey, accounts: &[AccountInfo], feed: FxFeed) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin = next_account_info(account_info_iter)?;
+        let registry_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let program_data = next_account_info(account_info_iter)?;
+
+        let (registry_key, bump_seed) = pda_feed_registry();
+        if *registry_account.key != registry_key {
+            return Err(FxError::InvalidRequest)?;
+        }
+
+        let mut registry: FxFeedRegistry = Self::administered(
+            program_id,
+            admin,
+            registry_account,
+            system_program,
+            program_data,
+            &[FEED_REGISTRY_SEED, &[bump_seed]],
+            Self::fx_feed_registry,
+        )?;
+
+        if feed.max_age == 0 {
+            return Err(FxError::InvalidRequest)?;
+        }
+
+        // Only a single feed per currency pair
+        if registry.feed(&feed.from_mint, &feed.to_mint).is_some() {
+            return Err(FxError::InvalidRequest)?;
+        }
+        if registry.feeds.len() >= MAX_FX_FEEDS {
+            return Err(FxError::RegistryFull)?;
+        }
+        registry.feeds.push(feed);
+        FxFeedRegistry::pack(registry, &mut registry_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn update_fx_feed(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        feed: FxFeed,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin = next_account_info(account_info_iter)?;
+        let registry_account = next_account_info(account_info_iter)?;
+
+        let mut registry = Self::fx_feed_registry(program_id, registry_account)?;
+        check_admin(admin, &registry.admin)?;
+
+        if feed.max_age == 0 {
+            return Err(FxError::InvalidRequest)?;
+        }
+
+        let entry = registry
+            .feeds
+            .iter_mut()
+            .find(|entry| entry.from_mint == feed.from_mint && entry.to_mint == feed.to_mint)
+            .ok_or(FxError::InvalidFxFeed)?;
+        *entry = feed;
+        FxFeedRegistry::pack(registry, &mut registry_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn remove_fx_feed(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        from_mint: &Pubkey,
+        to_mint: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin = next_account_info(account_info_iter)?;
+        let registry_account = next_account_info(account_info_iter)?;
+
+        let mut registry = Self::fx_feed_registry(program_id, registry_account)?;
+        check_admin(admin, &registry.admin)?;
+
+        let position = registry
+            .feeds
+            .iter()
+            .position(|entry| entry.from_mint == *from_mint && entry.to_mint == *to_mint)
+            .ok_or(FxError::InvalidFxFeed)?;
+        registry.feeds.remove(position);
+        FxFeedRegistry::pack(registry, &mut registry_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn set_base_mint(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        base_mint: Option<Pubkey>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let admin = next_account_info(account_info_iter)?;
+        let registry_account = next_account_info(account_info_iter)?;
+
+        let mut registry = Self::fx_feed_registry(program_id, registry_account)?;
+        check_admin(admin, &registry.admin)?;
+
+        registry.base_mint = base_mint;
+        FxFeedRegistry::pack(registry, &mut registry_account.try_borrow_mut_data()?)?;
+
+        Ok(())
+    }
+
+    fn migrate(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let payer = next_account_info(account_info_iter)?;
+        let fx_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let from_liquidity = next_account_info(account_info_iter)?;
+        let vault = next_account_info(account_info_iter)?;
+        let from_mint = next_account_info(account_info_iter)?;
+        let token = next_account_info(account_info_iter)?;
+        let rent_sysvar = next_account_info(account_info_iter)?;
+
+        if !payer.is_signer {
+            return Err(FxError::MissingSignature)?;
+        }
+        if fx_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // Only unversioned accounts require an upgrade
+        if fx_account.data_len() != FxDataV0::LEN {
+            FxData::unpack(&fx_account.try_borrow_data()?)?;
+            msg!("FX account is already up to date");
+            return Ok(());
+        }
+        let FxDataV0(fx_data) = FxDataV0::unpack(&fx_account.try_borrow_data()?)?;
+
+        // The sending liquidity provider was paid on initiation, so it escrows the amount again
+        if *from_liquidity.key != fx_data.from_liquidity {
+            return Err(FxError::InvalidRequest)?;
+        }
+        if !from_liquidity.is_signer {
+            return Err(FxError::MissingSignature)?;
+        }
+        let (vault_key, vault_bump_seed) = pda_vault(fx_account.key);
+        if *vault.key != vault_key {
+            return Err(FxError::InvalidVault)?;
+        }
+        if *token.key != spl_token::id() {
+            return Err(FxError::InvalidTokenProgram)?;
+        }
+        if Account::unpack(&from_liquidity.try_borrow_data()?)?.mint != *from_mint.key {
+            return Err(FxError::MintMismatch)?;
+        }
+        let vault_seeds: &[&[u8]] = &[VAULT_SEED, fx_account.key.as_ref(), &[vault_bump_seed]];
+
+        // Create the vault, acting as its own authority
+        Self::create_pda_account(
+            payer,
+            vault,
+            system_program,
+            Account::LEN,
+            token.key,
+            vault_seeds,
+        )?;
+        let init_vault_ix = spl_token::instruction::initialize_account(
+            token.key,
+            vault.key,
+            from_mint.key,
+            vault.key,
         )?;
         invoke(
-            &to_swap,
+            &init_vault_ix,
             &[
-                to_liquidity.clone(),
-                to_account.clone(),
-                to_liquidity.clone(),
+                vault.clone(),
+                from_mint.clone(),
+                vault.clone(),
+                rent_sysvar.clone(),
             ],
         )?;
 
-        // Close the FX account
-        **initializer.lamports.borrow_mut() = initializer
-            .lamports()
-            .checked_add(fx_account.lamports())
-            .ok_or(FxError::InvalidAmount)?;
-        **fx_account.lamports.borrow_mut() = 0;
-        *fx_account.try_borrow_mut_data()? = &mut [];
+        // Transfer the funds from `from_liquidity` -> `vault`
+        let escrow_ix = spl_token::instruction::transfer(
+            token.key,
+            from_liquidity.key,
+            vault.key,
+            from_liquidity.key,
+            &[],
+            fx_data.amount,
+        )?;
+        invoke(
+            &escrow_ix,
+            &[
+                from_liquidity.clone(),
+                vault.clone(),
+                from_liquidity.clone(),
+            ],
+        )?;
+
+        // Top up the rent of the larger account
+        let lamports = Rent::get()?
+            .minimum_balance(FxData::LEN)
+            .saturating_sub(fx_account.lamports());
+        if lamports > 0 {
+            invoke(
+                &system_instruction::transfer(payer.key, fx_account.key, lamports),
+                &[payer.clone(), fx_account.clone(), system_program.clone()],
+            )?;
+        }
+
+        fx_account.realloc(FxData::LEN, true)?;
+        FxData::pack(fx_data, &mut fx_account.try_borrow_mut_data()?)?;
 
         Ok(())
     }
+
+    /// Create a rent-exempt, program-owned account at the PDA of the [`seeds`], funded by the [`payer`]
+    fn create_program_account<'a>(
+        program_id: &Pubkey,
+        payer: &AccountInfo<'a>,
+        account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        len: usize,
+        seeds: &[&[u8]],
+    ) -> ProgramResult {
+        Self::create_pda_account(payer, account, system_program, len, program_id, seeds)
+    }
+
+    /// Create a rent-exempt account owned by [`owner`] at the PDA of the [`seeds`], funded by the [`payer`].
+    /// Anyone can send lamports to the PDA beforehand, which `create_account` refuses,
+    /// so an account already holding lamports is topped up, allocated & assigned instead.
+    fn create_pda_account<'a>(
+        payer: &AccountInfo<'a>,
+        account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        len: usize,
+        owner: &Pubkey,
+        seeds: &[&[u8]],
+    ) -> ProgramResult {
+        let lamports = Rent::get()?.minimum_balance(len);
+        if account.lamports() == 0 {
+            let create_account_ix = system_instruction::create_account(
+                payer.key,
+                account.key,
+                lamports,
+                len as u64,
+                owner,
+            );
+            return invoke_signed(
+                &create_account_ix,
+                &[payer.clone(), account.clone(), system_program.clone()],
+                &[seeds],
+            );
+        }
+
+        let shortfall = lamports.saturating_sub(account.lamports());
+        if shortfall > 0 {
+            let transfer_ix = system_instruction::transfer(payer.key, account.key, shortfall);
+            invoke(
+                &transfer_ix,
+                &[payer.clone(), account.clone(), system_program.clone()],
+            )?;
+        }
+        let allocate_ix = system_instruction::allocate(account.key, len as u64);
+        invoke_signed(
+            &allocate_ix,
+            &[account.clone(), system_program.clone()],
+            &[seeds],
+        )?;
+        let assign_ix = system_instruction::assign(account.key, owner);
+        invoke_signed(
+            &assign_ix,
+            &[account.clone(), system_program.clone()],
+            &[seeds],
+        )
+    }
+
+    /// Load a registry or the fee configuration for its administrator. Only the program's upgrade
+    /// authority creates it on first use, becoming its administrator.
+    fn administered<'a, T: Administered>(
+        program_id: &Pubkey,
+        admin: &AccountInfo<'a>,
+        account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        program_data: &AccountInfo<'a>,
+        seeds: &[&[u8]],
+        load: impl FnOnce(&Pubkey, &AccountInfo<'a>) -> Result<T, ProgramError>,
+    ) -> Result<T, ProgramError> {
+        if account.data_is_empty() {
+            check_upgrade_authority(admin, program_data)?;
+            Self::create_program_account(
+                program_id,
+                admin,
+                account,
+                system_program,
+                T::LEN,
+                seeds,
+            )?;
+            return Ok(T::new(*admin.key));
+        }
+        let state = load(program_id, account)?;
+        check_admin(admin, state.admin())?;
+        Ok(state)
+    }
+
+    /// Load the program's fee configuration, charging nothing until it's set
+    fn config(program_id: &Pubkey, config_account: &AccountInfo) -> Result<FxConfig, ProgramError> {
+        if *config_account.key != pda_config().0 {
+            return Err(FxError::InvalidRequest)?;
+        }
+        if config_account.data_is_empty() {
+            return Ok(FxConfig::default());
+        }
+        if config_account.owner != program_id {
+            return Err(FxError::InvalidRequest)?;
+        }
+        FxConfig::unpack(&config_account.try_borrow_data()?)
+    }
+
+    /// Load the program's registry of liquidity providers
+    fn liquidity_registry(
+        program_id: &Pubkey,
+        registry_account: &AccountInfo,
+    ) -> Result<LiquidityRegistry, ProgramError> {
+        if *registry_account.key != pda_liquidity_registry().0
+            || registry_account.owner != program_id
+        {
+            return Err(FxError::NoLiquidity)?;
+        }
+        LiquidityRegistry::unpack(&registry_account.try_borrow_data()?)
+    }
+
+    /// Load the program's registry of FX feeds
+    fn fx_feed_registry(
+        program_id: &Pubkey,
+        registry_account: &AccountInfo,
+    ) -> Result<FxFeedRegistry, ProgramError> {
+        if *registry_account.key != pda_feed_registry().0 || registry_account.owner != program_id {
+            return Err(FxError::InvalidFxFeed)?;
+        }
+        FxFeedRegistry::unpack(&registry_account.try_borrow_data()?)
+    }
 }
@@ -1,17 +1,101 @@
 use crate::error::FxError;
-use crate::liquidity::*;
+use crate::state::{FxFeed, FxFeedRegistry};
+use arrayref::{array_ref, array_refs};
+use borsh::{BorshDeserialize as Deserialize, BorshSerialize as Serialize};
 use const_decoder::Decoder;
 use num_traits::One;
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, RoundingStrategy};
 use solana_program::account_info::AccountInfo;
+use solana_program::clock::UnixTimestamp;
 use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
+use std::str::FromStr;
+
+/// An exchange rate quoted by an FX feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FxRate {
+    pub rate: Decimal,
+    // Feed round which provided the rate
+    pub round_id: u64,
+    // Round of the second feed of a cross rate
+    pub cross_round_id: Option<u64>,
+    // Publication time of the rate, if it can go stale
+    pub timestamp: Option<UnixTimestamp>,
+}
+
+impl FxRate {
+    /// Whether the rate was published more than [`max_age`] seconds before [`now`].
+    /// Ages beyond `i64::MAX` can never be exceeded, so they never go stale
+    pub fn is_stale(&self, now: UnixTimestamp, max_age: u64) -> bool {
+        match (self.timestamp, i64::try_from(max_age)) {
+            (Some(timestamp), Ok(max_age)) => now.saturating_sub(timestamp) > max_age,
+            _ => false,
+        }
+    }
+
+    /// Orient the rate quoted by a feed in the direction of the swap
+    pub fn directed(self, inverted: bool) -> Result<Self, FxError> {
+        if inverted {
+            let rate = Decimal::one()
+                .checked_div(self.rate)
+                .ok_or(FxError::InvalidFxFeed)?;
+            Ok(FxRate { rate, ..self })
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Chain this rate with the rate of [`next`], i.e. `a -> b` & `b -> c` into `a -> c`.
+    /// The combined rate is as old as the oldest of both, keeping the round of either feed.
+    pub fn cross(self, next: &FxRate) -> Result<Self, FxError> {
+        let rate = self
+            .rate
+            .checked_mul(next.rate)
+            .ok_or(FxError::InvalidRate)?;
+        let timestamp = match (self.timestamp, next.timestamp) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        Ok(FxRate {
+            rate,
+            round_id: self.round_id,
+            cross_round_id: Some(next.round_id),
+            timestamp,
+        })
+    }
+}
 
 pub trait FxRates {
     fn rate<'info>(
         fx_program: &AccountInfo<'info>,
         fx_feed: &AccountInfo<'info>,
-    ) -> Result<Decimal, ProgramError>;
+    ) -> Result<FxRate, ProgramError>;
+}
+
+/// Convert [`amount`] base units of a token with [`from_decimals`] at [`rate`]
+/// into base units of a token with [`to_decimals`].
+/// The result is rounded down, in favour of the liquidity provider.
+/// Like the converted amount, it's rejected above `i64::MAX`.
+pub fn convert_amount(
+    amount: u64,
+    rate: Decimal,
+    from_decimals: u8,
+    to_decimals: u8,
+) -> Result<u64, FxError> {
+    let amount = i64::try_from(amount).map_err(|_| FxError::InvalidAmount)?;
+    // Decimals beyond the maximum scale of a `Decimal`
+    if from_decimals > 28 || to_decimals > 28 {
+        return Err(FxError::InvalidAmount);
+    }
+    let to_unit = Decimal::from_i128_with_scale(10i128.pow(to_decimals as u32), 0);
+    let converted = Decimal::new(amount, from_decimals as u32)
+        .checked_mul(rate)
+        .and_then(|amount| amount.checked_mul(to_unit))
+        .ok_or(FxError::InvalidAmount)?
+        .round_dp_with_strategy(0, RoundingStrategy::ToZero);
+    i64::try_from(converted)
+        .map(|converted| converted as u64)
+        .map_err(|_| FxError::InvalidAmount)
 }
 
 /// FX rates based on the ChainLink FX oracle
@@ -21,12 +105,105 @@ impl FxRates for ChainLinkFx {
     fn rate<'info>(
         fx_program: &AccountInfo<'info>,
         fx_feed: &AccountInfo<'info>,
-    ) -> Result<Decimal, ProgramError> {
-        let rate = chainlink_solana::latest_round_data(fx_program.clone(), fx_feed.clone())?.answer;
+    ) -> Result<FxRate, ProgramError> {
+        let round = chainlink_solana::latest_round_data(fx_program.clone(), fx_feed.clone())?;
+        if round.answer <= 0 {
+            return Err(FxError::InvalidRate)?;
+        }
         let decimals = chainlink_solana::decimals(fx_program.clone(), fx_feed.clone())?;
-        let rate = Decimal::try_from_i128_with_scale(rate, decimals as u32)
+        let rate = Decimal::try_from_i128_with_scale(round.answer, decimals as u32)
             .map_err(|_| FxError::InvalidAmount)?;
-        Ok(rate)
+        Ok(FxRate {
+            rate,
+            round_id: round.round_id as u64,
+            cross_round_id: None,
+            timestamp: Some(round.timestamp as UnixTimestamp),
+        })
+    }
+}
+
+/// FX rates based on the Pyth oracle
+pub struct PythFx;
+
+impl PythFx {
+    const MAGIC: u32 = 0xa1b2c3d4;
+    const VERSION: u32 = 2;
+    const PRICE_ACCOUNT: u32 = 3;
+    const STATUS_TRADING: u32 = 1;
+    /// Length of the price account header up to & including the aggregate price
+    const PRICE_LEN: usize = 240;
+    /// Maximum confidence interval, in basis points of the price
+    pub const MAX_CONFIDENCE_BPS: u64 = 200;
+}
+
+impl FxRates for PythFx {
+    fn rate<'info>(
+        fx_program: &AccountInfo<'info>,
+        fx_feed: &AccountInfo<'info>,
+    ) -> Result<FxRate, ProgramError> {
+        if fx_feed.owner != fx_program.key {
+            return Err(FxError::InvalidFxFeed)?;
+        }
+        let data = fx_feed.try_borrow_data()?;
+        if data.len() < PythFx::PRICE_LEN {
+            return Err(FxError::InvalidFxFeed)?;
+        }
+        let src = array_ref![data, 0, PythFx::PRICE_LEN];
+        let (
+            magic,
+            version,
+            account_type,
+            _size_and_price_type,
+            exponent,
+            _slots_and_ema,
+            timestamp,
+            _previous,
+            price,
+            confidence,
+            status,
+            _corporate_action,
+            publish_slot,
+        ) = array_refs![src, 4, 4, 4, 8, 4, 72, 8, 104, 8, 8, 4, 4, 8];
+        if u32::from_le_bytes(*magic) != PythFx::MAGIC
+            || u32::from_le_bytes(*version) != PythFx::VERSION
+            || u32::from_le_bytes(*account_type) != PythFx::PRICE_ACCOUNT
+        {
+            return Err(FxError::InvalidFxFeed)?;
+        }
+
+        // Only use prices which are actively traded & within the confidence interval
+        if u32::from_le_bytes(*status) != PythFx::STATUS_TRADING {
+            return Err(FxError::InvalidRate)?;
+        }
+        let price = i64::from_le_bytes(*price);
+        if price <= 0 {
+            return Err(FxError::InvalidRate)?;
+        }
+        let confidence = u64::from_le_bytes(*confidence);
+        if confidence as u128 * 10_000 > price as u128 * PythFx::MAX_CONFIDENCE_BPS as u128 {
+            return Err(FxError::InvalidRate)?;
+        }
+
+        // Apply the exponent
+        let exponent = i32::from_le_bytes(*exponent);
+        let rate = if exponent <= 0 {
+            Decimal::try_from_i128_with_scale(price as i128, exponent.unsigned_abs())
+                .map_err(|_| FxError::InvalidRate)?
+        } else {
+            let factor = 10u64
+                .checked_pow(exponent as u32)
+                .ok_or(FxError::InvalidRate)?;
+            Decimal::from(price)
+                .checked_mul(Decimal::from(factor))
+                .ok_or(FxError::InvalidRate)?
+        };
+
+        Ok(FxRate {
+            rate,
+            round_id: u64::from_le_bytes(*publish_slot),
+            cross_round_id: None,
+            timestamp: Some(i64::from_le_bytes(*timestamp)),
+        })
     }
 }
 
@@ -50,25 +227,102 @@ pub const EUR_TO_USD: Pubkey = Pubkey::new_from_array(
     Decoder::Hex.decode(b"4d3aa429d67459fbfda97c6478366d8056121fdb0551a00696dbae25b0fc560d"),
 );
 
-pub fn feed_for_token(from_mint: &Pubkey, to_mint: &Pubkey) -> Option<Pubkey> {
-    match (*from_mint, *to_mint) {
-        (USD_MINT, EUR_MINT) => Some(USD_TO_EUR),
-        (EUR_MINT, USD_MINT) => Some(EUR_TO_USD),
-        (_, _) => None,
+/// The FX feeds quoting a currency pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FxRoute {
+    /// A single feed quoting the currency pair
+    Direct(FxFeed),
+    /// Two feeds quoting both currencies against the registry's base currency
+    Cross { from: FxFeed, to: FxFeed },
+}
+
+impl FxRoute {
+    /// The feed quoting the first leg of the route
+    pub fn feed(&self) -> &FxFeed {
+        match self {
+            FxRoute::Direct(feed) => feed,
+            FxRoute::Cross { from, .. } => from,
+        }
+    }
+
+    /// The feed quoting the second leg of a cross rate
+    pub fn cross_feed(&self) -> Option<&FxFeed> {
+        match self {
+            FxRoute::Direct(_) => None,
+            FxRoute::Cross { to, .. } => Some(to),
+        }
+    }
+
+    /// The oracle reading the feeds of the route
+    pub fn oracle(&self) -> Oracle {
+        self.feed().oracle
+    }
+
+    /// Maximum age of a rate along the route, in seconds
+    pub fn max_age(&self) -> u64 {
+        match self {
+            FxRoute::Direct(feed) => feed.max_age,
+            FxRoute::Cross { from, to } => from.max_age.min(to.max_age),
+        }
     }
 }
 
+/// The FX feeds registered for swapping [`from_mint`] -> [`to_mint`].
+/// Without a registered feed for the currency pair, the swap is routed through the registry's base currency.
+pub fn feed_for_token(
+    registry: &FxFeedRegistry,
+    from_mint: &Pubkey,
+    to_mint: &Pubkey,
+) -> Option<FxRoute> {
+    if let Some(feed) = registry.feed(from_mint, to_mint) {
+        return Some(FxRoute::Direct(*feed));
+    }
+
+    let base_mint = registry.base_mint?;
+    let from = registry.directed_feed(from_mint, &base_mint)?;
+    let to = registry.directed_feed(&base_mint, to_mint)?;
+    // Both legs are quoted through the same oracle
+    (from.oracle_program == to.oracle_program && from.oracle == to.oracle)
+        .then_some(FxRoute::Cross { from, to })
+}
+
 impl FxRates for StaticFx {
     fn rate<'info>(
         _fx_program: &AccountInfo<'info>,
         fx_feed: &AccountInfo<'info>,
-    ) -> Result<Decimal, ProgramError> {
+    ) -> Result<FxRate, ProgramError> {
         let usd_to_eur_rate = Decimal::new(9, 1);
-        match *fx_feed.key {
-            USD_TO_EUR => Ok(usd_to_eur_rate), //
-            EUR_TO_USD => Ok(Decimal::one() / usd_to_eur_rate),
-            _ => Err(FxError::InvalidFxFeed)?,
-        }
+        let rate = match *fx_feed.key {
+            USD_TO_EUR => usd_to_eur_rate, //
+            EUR_TO_USD => Decimal::one() / usd_to_eur_rate,
+            _ => return Err(FxError::InvalidFxFeed)?,
+        };
+        // Static rates never go stale
+        Ok(FxRate {
+            rate,
+            round_id: 0,
+            cross_round_id: None,
+            timestamp: None,
+        })
+    }
+}
+
+/// FX rates derived through a base currency, i.e. `from -> base -> to`.
+/// Unlike the other [`FxRates`], the rate is quoted by two feeds of the same oracle.
+pub struct CrossRateFx;
+
+impl CrossRateFx {
+    pub fn rate<'info>(
+        oracle: &Oracle,
+        fx_program: &AccountInfo<'info>,
+        (from_feed, from_inverted): (&AccountInfo<'info>, bool),
+        (to_feed, to_inverted): (&AccountInfo<'info>, bool),
+    ) -> Result<FxRate, ProgramError> {
+        let from = oracle
+            .rate(fx_program, from_feed)?
+            .directed(from_inverted)?;
+        let to = oracle.rate(fx_program, to_feed)?.directed(to_inverted)?;
+        Ok(from.cross(&to)?)
     }
 }
 
@@ -79,7 +333,7 @@ impl FxRates for DemoFx {
     fn rate<'info>(
         fx_program: &AccountInfo<'info>,
         fx_feed: &AccountInfo<'info>,
-    ) -> Result<Decimal, ProgramError> {
+    ) -> Result<FxRate, ProgramError> {
         if StaticFx::is_demo(fx_feed.key) {
             StaticFx::rate(fx_program, fx_feed)
         } else {
@@ -87,3 +341,71 @@ impl FxRates for DemoFx {
         }
     }
 }
+
+/// The oracle providing the exchange rate of a swap
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Oracle {
+    Demo,
+    ChainLink,
+    Pyth,
+}
+
+impl Oracle {
+    /// Whether the rate of [`fx_feed`] is read from the feed account alone.
+    /// Otherwise it's returned by a CPI into the oracle program, which can only be evaluated on-chain.
+    pub fn reads_feed_account(&self, fx_feed: &Pubkey) -> bool {
+        match self {
+            Oracle::Demo => StaticFx::is_demo(fx_feed),
+            Oracle::ChainLink => false,
+            Oracle::Pyth => true,
+        }
+    }
+
+    pub fn rate<'info>(
+        &self,
+        fx_program: &AccountInfo<'info>,
+        fx_feed: &AccountInfo<'info>,
+    ) -> Result<FxRate, ProgramError> {
+        match self {
+            Oracle::Demo => DemoFx::rate(fx_program, fx_feed),
+            Oracle::ChainLink => ChainLinkFx::rate(fx_program, fx_feed),
+            Oracle::Pyth => PythFx::rate(fx_program, fx_feed),
+        }
+    }
+}
+
+impl From<Oracle> for u8 {
+    fn from(oracle: Oracle) -> Self {
+        match oracle {
+            Oracle::Demo => 0,
+            Oracle::ChainLink => 1,
+            Oracle::Pyth => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for Oracle {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Oracle::Demo),
+            1 => Ok(Oracle::ChainLink),
+            2 => Ok(Oracle::Pyth),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+}
+
+impl FromStr for Oracle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "demo" => Ok(Oracle::Demo),
+            "chainlink" => Ok(Oracle::ChainLink),
+            "pyth" => Ok(Oracle::Pyth),
+            _ => Err(format!("Unknown oracle {}", s)),
+        }
+    }
+}
@@ -1,4 +1,7 @@
+use crate::error::FxError;
+use crate::rates::{FxRate, Oracle};
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use borsh::{BorshDeserialize as Deserialize, BorshSerialize as Serialize};
 use rust_decimal::Decimal;
 use solana_program::clock::UnixTimestamp;
 use solana_program::program_error::ProgramError;
@@ -6,6 +9,7 @@ use solana_program::program_pack::{IsInitialized, Pack, Sealed};
 use solana_program::pubkey::Pubkey;
 use std::mem::size_of;
 use std::ops::Range;
+use std::str::FromStr;
 
 #[derive(Debug)]
 pub struct FxData {
@@ -22,16 +26,329 @@ pub struct FxData {
 
     // Swap parameters
     pub amount: u64,
+    // Part of the [`amount`] settled so far
+    pub amount_filled: u64,
+    pub from_decimals: u8,
+    pub to_decimals: u8,
     pub limits: Range<Decimal>,
     pub valid_until: UnixTimestamp,
+    pub kind: SwapKind,
+    pub expiry: ExpiryPolicy,
+    // Initializer's source token account, refunded on expiry
+    pub refund_account: Pubkey,
+    // Lamports paid to whoever executes the swap, held by the FX account
+    pub crank_reward: u64,
+    // Record executions in a [`SettlementReceipt`], whose rent is held by the FX account until created
+    pub receipt: bool,
 
     // FX feed
+    pub oracle: Oracle,
     pub fx_feed: Pubkey,
+    pub fx_feed_inverted: bool,
+    pub oracle_program: Pubkey,
+    pub max_rate_age: u64,
+    // Second FX feed of a cross rate
+    pub cross_feed: Option<Pubkey>,
+    pub cross_feed_inverted: bool,
 }
 
+/// The exchange rates at which a swap executes before it expires
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapKind {
+    /// One-cancels-other: take the profit at or above the upper limit, or stop the loss at or below the lower limit
+    Oco,
+    /// Take the profit at or above the upper limit
+    Limit,
+    /// Stop the loss at or below the lower limit
+    Stop,
+}
+
+impl SwapKind {
+    /// Whether [`rate`] triggers a swap with the given [`limits`]
+    pub fn is_triggered(&self, limits: &Range<Decimal>, rate: Decimal) -> bool {
+        let take_profit = rate >= limits.end;
+        let stop_loss = rate <= limits.start;
+        match self {
+            SwapKind::Oco => take_profit || stop_loss,
+            SwapKind::Limit => take_profit,
+            SwapKind::Stop => stop_loss,
+        }
+    }
+}
+
+// Accounts predating swap kinds hold a zero byte, i.e. [`SwapKind::Oco`]
+impl From<SwapKind> for u8 {
+    fn from(kind: SwapKind) -> Self {
+        match kind {
+            SwapKind::Oco => 0,
+            SwapKind::Limit => 1,
+            SwapKind::Stop => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for SwapKind {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(SwapKind::Oco),
+            1 => Ok(SwapKind::Limit),
+            2 => Ok(SwapKind::Stop),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+}
+
+impl FromStr for SwapKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "oco" => Ok(SwapKind::Oco),
+            "limit" => Ok(SwapKind::Limit),
+            "stop" => Ok(SwapKind::Stop),
+            _ => Err(format!("Unknown swap kind {}", s)),
+        }
+    }
+}
+
+/// Why a swap executed
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// The rate reached the upper limit
+    UpperLimit,
+    /// The rate reached the lower limit
+    LowerLimit,
+    /// The swap expired & settled at the market rate
+    Expiry,
+}
+
+impl From<Trigger> for u8 {
+    fn from(trigger: Trigger) -> Self {
+        match trigger {
+            Trigger::UpperLimit => 0,
+            Trigger::LowerLimit => 1,
+            Trigger::Expiry => 2,
+        }
+    }
+}
+
+impl TryFrom<u8> for Trigger {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Trigger::UpperLimit),
+            1 => Ok(Trigger::LowerLimit),
+            2 => Ok(Trigger::Expiry),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+}
+
+/// What happens to a swap which wasn't triggered before it expired
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpiryPolicy {
+    /// Settle the swap at the market rate
+    SettleAtMarket,
+    /// Refuse to execute the swap, leaving it to be refunded to the initializer
+    Refund,
+}
+
+impl From<ExpiryPolicy> for u8 {
+    fn from(expiry: ExpiryPolicy) -> Self {
+        match expiry {
+            ExpiryPolicy::SettleAtMarket => 0,
+            ExpiryPolicy::Refund => 1,
+        }
+    }
+}
+
+impl TryFrom<u8> for ExpiryPolicy {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ExpiryPolicy::SettleAtMarket),
+            1 => Ok(ExpiryPolicy::Refund),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+}
+
+impl FromStr for ExpiryPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "settle" => Ok(ExpiryPolicy::SettleAtMarket),
+            "refund" => Ok(ExpiryPolicy::Refund),
+            _ => Err(format!("Unknown expiry policy {}", s)),
+        }
+    }
+}
+
+impl FxData {
+    /// Part of the [`amount`] which remains to be settled
+    pub fn amount_remaining(&self) -> u64 {
+        self.amount.saturating_sub(self.amount_filled)
+    }
+
+    /// Why the swap executes at the [`quote`] at [`now`], without touching any account.
+    /// The program runs this check on execution, so off-chain callers can evaluate it before submitting
+    pub fn check_execution(&self, quote: &FxRate, now: UnixTimestamp) -> Result<Trigger, FxError> {
+        if quote.is_stale(now, self.max_rate_age) {
+            return Err(FxError::StaleRate);
+        }
+        if self.valid_until > now {
+            if !self.kind.is_triggered(&self.limits, quote.rate) {
+                return Err(FxError::SwapConditionsNotMet);
+            }
+            Ok(if quote.rate >= self.limits.end {
+                Trigger::UpperLimit
+            } else {
+                Trigger::LowerLimit
+            })
+        } else if self.expiry == ExpiryPolicy::Refund {
+            Err(FxError::SwapExpired)
+        } else {
+            Ok(Trigger::Expiry)
+        }
+    }
+}
+
+/// Layout version of [`FxData`] accounts
+pub const FX_DATA_VERSION: u8 = 1;
+
+/// Offset of [`FxData::initializer`] in a packed account, following the version & initialized flag
+pub const FX_DATA_INITIALIZER_OFFSET: usize = size_of::<u8>() + size_of::<bool>();
+
+/// Offset of [`FxData::to_holding`] in a packed account, following the initializer & sending holding
+pub const FX_DATA_TO_HOLDING_OFFSET: usize = FX_DATA_INITIALIZER_OFFSET + 2 * size_of::<Pubkey>();
+
+/// Space reserved for the fields of later [`FxData`] layout versions
+const FX_DATA_RESERVED: usize = 205;
+
 impl Sealed for FxData {}
 
 impl Pack for FxData {
+    const LEN: usize = size_of::<u8>()
+        + FX_DATA_FIELDS_LEN
+        + size_of::<u64>()
+        + 2 * size_of::<u8>()
+        + size_of::<Pubkey>()
+        + size_of::<u64>()
+        + size_of::<bool>()
+        + FX_DATA_RESERVED;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, FxData::LEN];
+        let (
+            version,
+            fields,
+            amount_filled,
+            kind,
+            expiry,
+            refund_account,
+            crank_reward,
+            receipt,
+            reserved,
+        ) = mut_array_refs![
+            dst,
+            size_of::<u8>(),
+            FX_DATA_FIELDS_LEN,
+            size_of::<u64>(),
+            size_of::<u8>(),
+            size_of::<u8>(),
+            size_of::<Pubkey>(),
+            size_of::<u64>(),
+            size_of::<bool>(),
+            FX_DATA_RESERVED
+        ];
+
+        version[0] = FX_DATA_VERSION;
+        pack_fields(self, fields);
+        *amount_filled = self.amount_filled.to_be_bytes();
+        kind[0] = self.kind.into();
+        expiry[0] = self.expiry.into();
+        refund_account.copy_from_slice(self.refund_account.as_ref());
+        *crank_reward = self.crank_reward.to_be_bytes();
+        receipt[0] = self.receipt as u8;
+        reserved.fill(0);
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, FxData::LEN];
+        let (
+            version,
+            fields,
+            amount_filled,
+            kind,
+            expiry,
+            refund_account,
+            crank_reward,
+            receipt,
+            _reserved,
+        ) = array_refs![
+            src,
+            size_of::<u8>(),
+            FX_DATA_FIELDS_LEN,
+            size_of::<u64>(),
+            size_of::<u8>(),
+            size_of::<u8>(),
+            size_of::<Pubkey>(),
+            size_of::<u64>(),
+            size_of::<bool>(),
+            FX_DATA_RESERVED
+        ];
+        let receipt = match receipt {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let data = FxData {
+            amount_filled: u64::from_be_bytes(*amount_filled),
+            kind: SwapKind::try_from(kind[0])?,
+            expiry: ExpiryPolicy::try_from(expiry[0])?,
+            refund_account: Pubkey::from(*refund_account),
+            crank_reward: u64::from_be_bytes(*crank_reward),
+            receipt,
+            ..unpack_fields(fields)?
+        };
+        match version[0] {
+            // Freshly allocated account, not yet written by the program
+            0 if !data.is_initialized => Ok(data),
+            FX_DATA_VERSION => Ok(data),
+            _ => Err(FxError::UnknownVersion.into()),
+        }
+    }
+
+    fn unpack_unchecked(input: &[u8]) -> Result<Self, ProgramError> {
+        match input.len() {
+            FxData::LEN => Self::unpack_from_slice(input),
+            FxDataV0::LEN => Err(FxError::MigrationRequired.into()),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+}
+
+impl IsInitialized for FxData {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+/// The unversioned [`FxData`] layout of the accounts created by the original program,
+/// which held the swap's fields up to its FX feed, before the version byte was introduced.
+/// Those swaps paid their amount to the sending liquidity provider on initiation & executed at the demo rate,
+/// without scaling amounts by the mint decimals.
+#[derive(Debug)]
+pub struct FxDataV0(pub FxData);
+
+impl Sealed for FxDataV0 {}
+
+impl Pack for FxDataV0 {
     const LEN: usize = size_of::<bool>()
         + 5 * size_of::<Pubkey>()
         + size_of::<u64>()
@@ -40,7 +357,7 @@ impl Pack for FxData {
         + size_of::<Pubkey>();
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
-        let dst = array_mut_ref![dst, 0, FxData::LEN];
+        let dst = array_mut_ref![dst, 0, FxDataV0::LEN];
         let (
             is_initialized,
             initializer_public_key,
@@ -68,21 +385,23 @@ impl Pack for FxData {
             size_of::<Pubkey>()
         ];
 
-        is_initialized[0] = self.is_initialized as u8;
-        initializer_public_key.copy_from_slice(self.initializer.as_ref());
-        from_holding_account_public_key.copy_from_slice(self.from_holding.as_ref());
-        to_holding_account_public_key.copy_from_slice(self.to_holding.as_ref());
-        from_liquidity.copy_from_slice(self.from_liquidity.as_ref());
-        to_liquidity.copy_from_slice(self.to_liquidity.as_ref());
-        *amount = self.amount.to_be_bytes();
-        upper_limit.copy_from_slice(&self.limits.end.serialize());
-        lower_limit.copy_from_slice(&self.limits.start.serialize());
-        *valid_until = self.valid_until.to_be_bytes();
-        fx_feed.copy_from_slice(self.fx_feed.as_ref());
+        let data = &self.0;
+        is_initialized[0] = data.is_initialized as u8;
+        initializer_public_key.copy_from_slice(data.initializer.as_ref());
+        from_holding_account_public_key.copy_from_slice(data.from_holding.as_ref());
+        to_holding_account_public_key.copy_from_slice(data.to_holding.as_ref());
+        from_liquidity.copy_from_slice(data.from_liquidity.as_ref());
+        to_liquidity.copy_from_slice(data.to_liquidity.as_ref());
+        *amount = data.amount.to_be_bytes();
+        upper_limit.copy_from_slice(&data.limits.end.serialize());
+        lower_limit.copy_from_slice(&data.limits.start.serialize());
+        *valid_until = data.valid_until.to_be_bytes();
+        fx_feed.copy_from_slice(data.fx_feed.as_ref());
     }
 
+    /// Unpack the swap with the defaults matching the original program's behaviour
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let src = array_ref![src, 0, FxData::LEN];
+        let src = array_ref![src, 0, FxDataV0::LEN];
         let (
             is_initialized,
             initializer,
@@ -114,7 +433,7 @@ impl Pack for FxData {
             [1] => true,
             _ => return Err(ProgramError::InvalidAccountData),
         };
-        Ok(Self {
+        Ok(Self(FxData {
             is_initialized,
             initializer: Pubkey::from(*initializer),
             from_holding: Pubkey::from(*from_holding_account_public_key),
@@ -122,15 +441,766 @@ impl Pack for FxData {
             from_liquidity: Pubkey::from(*from_liquidity),
             to_liquidity: Pubkey::from(*to_liquidity),
             amount: u64::from_be_bytes(*amount),
+            amount_filled: 0,
+            // Amounts were converted at the rate, without scaling by the mint decimals
+            from_decimals: 0,
+            to_decimals: 0,
             limits: Decimal::deserialize(*lower_limit)..Decimal::deserialize(*upper_limit),
             valid_until: UnixTimestamp::from_be_bytes(*valid_until),
+            kind: SwapKind::Oco,
+            expiry: ExpiryPolicy::SettleAtMarket,
+            // The source account was closed on initiation
+            refund_account: Pubkey::default(),
+            crank_reward: 0,
+            receipt: false,
+            oracle: Oracle::Demo,
             fx_feed: Pubkey::from(*fx_feed_owner),
+            fx_feed_inverted: false,
+            oracle_program: Pubkey::default(),
+            // The demo rates never go stale
+            max_rate_age: u64::MAX,
+            cross_feed: None,
+            cross_feed_inverted: false,
+        }))
+    }
+}
+
+impl IsInitialized for FxDataV0 {
+    fn is_initialized(&self) -> bool {
+        self.0.is_initialized
+    }
+}
+
+/// Length of the swap's fields, following the version byte of a packed [`FxData`]
+const FX_DATA_FIELDS_LEN: usize = size_of::<bool>()
+    + 5 * size_of::<Pubkey>()
+    + size_of::<u64>()
+    + 2 * size_of::<u8>()
+    + 2 * size_of::<Decimal>()
+    + size_of::<UnixTimestamp>()
+    + size_of::<u8>()
+    + size_of::<Pubkey>()
+    + size_of::<bool>()
+    + size_of::<Pubkey>()
+    + size_of::<u64>()
+    + size_of::<Pubkey>()
+    + size_of::<bool>();
+
+/// The swap's fields, following the version byte of a packed [`FxData`]
+fn pack_fields(data: &FxData, dst: &mut [u8; FX_DATA_FIELDS_LEN]) {
+    let (
+        is_initialized,
+        initializer_public_key,
+        from_holding_account_public_key,
+        to_holding_account_public_key,
+        from_liquidity,
+        to_liquidity,
+        amount,
+        from_decimals,
+        to_decimals,
+        upper_limit,
+        lower_limit,
+        valid_until,
+        oracle,
+        fx_feed,
+        fx_feed_inverted,
+        oracle_program,
+        max_rate_age,
+        cross_feed,
+        cross_feed_inverted,
+    ) = mut_array_refs![
+        dst,
+        size_of::<bool>(),
+        size_of::<Pubkey>(),
+        size_of::<Pubkey>(),
+        size_of::<Pubkey>(),
+        size_of::<Pubkey>(),
+        size_of::<Pubkey>(),
+        size_of::<u64>(),
+        size_of::<u8>(),
+        size_of::<u8>(),
+        size_of::<Decimal>(),
+        size_of::<Decimal>(),
+        size_of::<UnixTimestamp>(),
+        size_of::<u8>(),
+        size_of::<Pubkey>(),
+        size_of::<bool>(),
+        size_of::<Pubkey>(),
+        size_of::<u64>(),
+        size_of::<Pubkey>(),
+        size_of::<bool>()
+    ];
+
+    is_initialized[0] = data.is_initialized as u8;
+    initializer_public_key.copy_from_slice(data.initializer.as_ref());
+    from_holding_account_public_key.copy_from_slice(data.from_holding.as_ref());
+    to_holding_account_public_key.copy_from_slice(data.to_holding.as_ref());
+    from_liquidity.copy_from_slice(data.from_liquidity.as_ref());
+    to_liquidity.copy_from_slice(data.to_liquidity.as_ref());
+    *amount = data.amount.to_be_bytes();
+    from_decimals[0] = data.from_decimals;
+    to_decimals[0] = data.to_decimals;
+    upper_limit.copy_from_slice(&data.limits.end.serialize());
+    lower_limit.copy_from_slice(&data.limits.start.serialize());
+    *valid_until = data.valid_until.to_be_bytes();
+    oracle[0] = data.oracle.into();
+    fx_feed.copy_from_slice(data.fx_feed.as_ref());
+    fx_feed_inverted[0] = data.fx_feed_inverted as u8;
+    oracle_program.copy_from_slice(data.oracle_program.as_ref());
+    *max_rate_age = data.max_rate_age.to_be_bytes();
+    pack_optional_key(&data.cross_feed, cross_feed);
+    cross_feed_inverted[0] = data.cross_feed_inverted as u8;
+}
+
+fn unpack_fields(src: &[u8; FX_DATA_FIELDS_LEN]) -> Result<FxData, ProgramError> {
+    let (
+        is_initialized,
+        initializer,
+        from_holding_account_public_key,
+        to_holding_account_public_key,
+        from_liquidity,
+        to_liquidity,
+        amount,
+        from_decimals,
+        to_decimals,
+        upper_limit,
+        lower_limit,
+        valid_until,
+        oracle,
+        fx_feed_owner,
+        fx_feed_inverted,
+        oracle_program,
+        max_rate_age,
+        cross_feed,
+        cross_feed_inverted,
+    ) = array_refs![
+        src,
+        size_of::<bool>(),
+        size_of::<Pubkey>(),
+        size_of::<Pubkey>(),
+        size_of::<Pubkey>(),
+        size_of::<Pubkey>(),
+        size_of::<Pubkey>(),
+        size_of::<u64>(),
+        size_of::<u8>(),
+        size_of::<u8>(),
+        size_of::<Decimal>(),
+        size_of::<Decimal>(),
+        size_of::<UnixTimestamp>(),
+        size_of::<u8>(),
+        size_of::<Pubkey>(),
+        size_of::<bool>(),
+        size_of::<Pubkey>(),
+        size_of::<u64>(),
+        size_of::<Pubkey>(),
+        size_of::<bool>()
+    ];
+    let is_initialized = match is_initialized {
+        [0] => false,
+        [1] => true,
+        _ => return Err(ProgramError::InvalidAccountData),
+    };
+    let fx_feed_inverted = match fx_feed_inverted {
+        [0] => false,
+        [1] => true,
+        _ => return Err(ProgramError::InvalidAccountData),
+    };
+    let cross_feed_inverted = match cross_feed_inverted {
+        [0] => false,
+        [1] => true,
+        _ => return Err(ProgramError::InvalidAccountData),
+    };
+    Ok(FxData {
+        is_initialized,
+        initializer: Pubkey::from(*initializer),
+        from_holding: Pubkey::from(*from_holding_account_public_key),
+        to_holding: Pubkey::from(*to_holding_account_public_key),
+        from_liquidity: Pubkey::from(*from_liquidity),
+        to_liquidity: Pubkey::from(*to_liquidity),
+        amount: u64::from_be_bytes(*amount),
+        amount_filled: 0,
+        from_decimals: from_decimals[0],
+        to_decimals: to_decimals[0],
+        limits: Decimal::deserialize(*lower_limit)..Decimal::deserialize(*upper_limit),
+        valid_until: UnixTimestamp::from_be_bytes(*valid_until),
+        kind: SwapKind::Oco,
+        expiry: ExpiryPolicy::SettleAtMarket,
+        refund_account: Pubkey::default(),
+        crank_reward: 0,
+        receipt: false,
+        oracle: Oracle::try_from(oracle[0])?,
+        fx_feed: Pubkey::from(*fx_feed_owner),
+        fx_feed_inverted,
+        oracle_program: Pubkey::from(*oracle_program),
+        max_rate_age: u64::from_be_bytes(*max_rate_age),
+        cross_feed: unpack_optional_key(cross_feed),
+        cross_feed_inverted,
+    })
+}
+
+/// The program's registries & fee configuration, created by its upgrade authority & managed by their administrator
+pub trait Administered: Pack {
+    /// An empty account managed by [`admin`]
+    fn new(admin: Pubkey) -> Self;
+
+    fn admin(&self) -> &Pubkey;
+}
+
+/// Maximum number of liquidity accounts a [`LiquidityRegistry`] can hold
+pub const MAX_LIQUIDITY_PROVIDERS: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiquidityEntry {
+    pub mint: Pubkey,
+    pub liquidity: Pubkey,
+}
+
+impl LiquidityEntry {
+    const LEN: usize = 2 * size_of::<Pubkey>();
+}
+
+#[derive(Debug, Default)]
+pub struct LiquidityRegistry {
+    pub is_initialized: bool,
+    // Registry administrator
+    pub admin: Pubkey,
+    // Registered liquidity token accounts
+    pub providers: Vec<LiquidityEntry>,
+}
+
+impl Sealed for LiquidityRegistry {}
+
+impl Pack for LiquidityRegistry {
+    const LEN: usize = size_of::<bool>()
+        + size_of::<Pubkey>()
+        + size_of::<u8>()
+        + MAX_LIQUIDITY_PROVIDERS * LiquidityEntry::LEN;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, LiquidityRegistry::LEN];
+        let (is_initialized, admin, count, providers) = mut_array_refs![
+            dst,
+            size_of::<bool>(),
+            size_of::<Pubkey>(),
+            size_of::<u8>(),
+            MAX_LIQUIDITY_PROVIDERS * LiquidityEntry::LEN
+        ];
+
+        is_initialized[0] = self.is_initialized as u8;
+        admin.copy_from_slice(self.admin.as_ref());
+        count[0] = self.providers.len() as u8;
+        providers.fill(0);
+        for (entry, dst) in self
+            .providers
+            .iter()
+            .zip(providers.chunks_exact_mut(LiquidityEntry::LEN))
+        {
+            let dst = array_mut_ref![dst, 0, LiquidityEntry::LEN];
+            let (mint, liquidity) = mut_array_refs![dst, size_of::<Pubkey>(), size_of::<Pubkey>()];
+            mint.copy_from_slice(entry.mint.as_ref());
+            liquidity.copy_from_slice(entry.liquidity.as_ref());
+        }
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, LiquidityRegistry::LEN];
+        let (is_initialized, admin, count, providers) = array_refs![
+            src,
+            size_of::<bool>(),
+            size_of::<Pubkey>(),
+            size_of::<u8>(),
+            MAX_LIQUIDITY_PROVIDERS * LiquidityEntry::LEN
+        ];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let count = count[0] as usize;
+        if count > MAX_LIQUIDITY_PROVIDERS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let providers = providers
+            .chunks_exact(LiquidityEntry::LEN)
+            .take(count)
+            .map(|src| {
+                let src = array_ref![src, 0, LiquidityEntry::LEN];
+                let (mint, liquidity) = array_refs![src, size_of::<Pubkey>(), size_of::<Pubkey>()];
+                LiquidityEntry {
+                    mint: Pubkey::from(*mint),
+                    liquidity: Pubkey::from(*liquidity),
+                }
+            })
+            .collect();
+        Ok(Self {
+            is_initialized,
+            admin: Pubkey::from(*admin),
+            providers,
         })
     }
 }
 
-impl IsInitialized for FxData {
+impl IsInitialized for LiquidityRegistry {
     fn is_initialized(&self) -> bool {
         self.is_initialized
     }
 }
+
+impl Administered for LiquidityRegistry {
+    fn new(admin: Pubkey) -> Self {
+        LiquidityRegistry {
+            is_initialized: true,
+            admin,
+            ..LiquidityRegistry::default()
+        }
+    }
+
+    fn admin(&self) -> &Pubkey {
+        &self.admin
+    }
+}
+
+/// Maximum number of FX feeds a [`FxFeedRegistry`] can hold
+pub const MAX_FX_FEEDS: usize = 32;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FxFeed {
+    // Currency pair
+    pub from_mint: Pubkey,
+    pub to_mint: Pubkey,
+
+    // Feed providing the exchange rate
+    pub feed: Pubkey,
+    pub oracle_program: Pubkey,
+    // Oracle reading the feed, which swaps of the currency pair need to select
+    pub oracle: Oracle,
+    // The feed quotes [`to_mint`] -> [`from_mint`]
+    pub inverted: bool,
+    // Maximum age of a quoted rate, in seconds
+    pub max_age: u64,
+}
+
+impl FxFeed {
+    const LEN: usize =
+        4 * size_of::<Pubkey>() + size_of::<u8>() + size_of::<bool>() + size_of::<u64>();
+}
+
+#[derive(Debug, Default)]
+pub struct FxFeedRegistry {
+    pub is_initialized: bool,
+    // Registry administrator
+    pub admin: Pubkey,
+    // Currency to derive cross rates through
+    pub base_mint: Option<Pubkey>,
+    // Registered FX feeds
+    pub feeds: Vec<FxFeed>,
+}
+
+impl FxFeedRegistry {
+    pub fn feed(&self, from_mint: &Pubkey, to_mint: &Pubkey) -> Option<&FxFeed> {
+        self.feeds
+            .iter()
+            .find(|feed| feed.from_mint == *from_mint && feed.to_mint == *to_mint)
+    }
+
+    /// The feed quoting [`from_mint`] -> [`to_mint`], falling back to inverting the opposite currency pair
+    pub fn directed_feed(&self, from_mint: &Pubkey, to_mint: &Pubkey) -> Option<FxFeed> {
+        self.feed(from_mint, to_mint).copied().or_else(|| {
+            self.feed(to_mint, from_mint).map(|feed| FxFeed {
+                from_mint: *from_mint,
+                to_mint: *to_mint,
+                inverted: !feed.inverted,
+                ..*feed
+            })
+        })
+    }
+}
+
+impl Sealed for FxFeedRegistry {}
+
+impl Pack for FxFeedRegistry {
+    const LEN: usize =
+        size_of::<bool>() + 2 * size_of::<Pubkey>() + size_of::<u8>() + MAX_FX_FEEDS * FxFeed::LEN;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, FxFeedRegistry::LEN];
+        let (is_initialized, admin, base_mint, count, feeds) = mut_array_refs![
+            dst,
+            size_of::<bool>(),
+            size_of::<Pubkey>(),
+            size_of::<Pubkey>(),
+            size_of::<u8>(),
+            MAX_FX_FEEDS * FxFeed::LEN
+        ];
+
+        is_initialized[0] = self.is_initialized as u8;
+        admin.copy_from_slice(self.admin.as_ref());
+        pack_optional_key(&self.base_mint, base_mint);
+        count[0] = self.feeds.len() as u8;
+        feeds.fill(0);
+        for (entry, dst) in self.feeds.iter().zip(feeds.chunks_exact_mut(FxFeed::LEN)) {
+            let dst = array_mut_ref![dst, 0, FxFeed::LEN];
+            let (from_mint, to_mint, feed, oracle_program, oracle, inverted, max_age) = mut_array_refs![
+                dst,
+                size_of::<Pubkey>(),
+                size_of::<Pubkey>(),
+                size_of::<Pubkey>(),
+                size_of::<Pubkey>(),
+                size_of::<u8>(),
+                size_of::<bool>(),
+                size_of::<u64>()
+            ];
+            from_mint.copy_from_slice(entry.from_mint.as_ref());
+            to_mint.copy_from_slice(entry.to_mint.as_ref());
+            feed.copy_from_slice(entry.feed.as_ref());
+            oracle_program.copy_from_slice(entry.oracle_program.as_ref());
+            oracle[0] = entry.oracle.into();
+            inverted[0] = entry.inverted as u8;
+            *max_age = entry.max_age.to_be_bytes();
+        }
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, FxFeedRegistry::LEN];
+        let (is_initialized, admin, base_mint, count, feeds) = array_refs![
+            src,
+            size_of::<bool>(),
+            size_of::<Pubkey>(),
+            size_of::<Pubkey>(),
+            size_of::<u8>(),
+            MAX_FX_FEEDS * FxFeed::LEN
+        ];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let count = count[0] as usize;
+        if count > MAX_FX_FEEDS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let feeds = feeds
+            .chunks_exact(FxFeed::LEN)
+            .take(count)
+            .map(|src| {
+                let src = array_ref![src, 0, FxFeed::LEN];
+                let (from_mint, to_mint, feed, oracle_program, oracle, inverted, max_age) = array_refs![
+                    src,
+                    size_of::<Pubkey>(),
+                    size_of::<Pubkey>(),
+                    size_of::<Pubkey>(),
+                    size_of::<Pubkey>(),
+                    size_of::<u8>(),
+                    size_of::<bool>(),
+                    size_of::<u64>()
+                ];
+                let inverted = match inverted {
+                    [0] => false,
+                    [1] => true,
+                    _ => return Err(ProgramError::InvalidAccountData),
+                };
+                Ok(FxFeed {
+                    from_mint: Pubkey::from(*from_mint),
+                    to_mint: Pubkey::from(*to_mint),
+                    feed: Pubkey::from(*feed),
+                    oracle_program: Pubkey::from(*oracle_program),
+                    oracle: Oracle::try_from(oracle[0])?,
+                    inverted,
+                    max_age: u64::from_be_bytes(*max_age),
+                })
+            })
+            .collect::<Result<_, ProgramError>>()?;
+        Ok(Self {
+            is_initialized,
+            admin: Pubkey::from(*admin),
+            base_mint: unpack_optional_key(base_mint),
+            feeds,
+        })
+    }
+}
+
+impl IsInitialized for FxFeedRegistry {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Administered for FxFeedRegistry {
+    fn new(admin: Pubkey) -> Self {
+        FxFeedRegistry {
+            is_initialized: true,
+            admin,
+            ..FxFeedRegistry::default()
+        }
+    }
+
+    fn admin(&self) -> &Pubkey {
+        &self.admin
+    }
+}
+
+/// Basis points in a whole
+pub const BPS: u16 = 10_000;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FxConfig {
+    pub is_initialized: bool,
+    // Config administrator
+    pub admin: Pubkey,
+    // Protocol fee on the converted amount, in basis points
+    pub fee_bps: u16,
+    // Spread retained by the liquidity provider on the converted amount, in basis points
+    pub spread_bps: u16,
+    // Owner of the token accounts collecting the protocol fee
+    pub fee_collector: Pubkey,
+}
+
+/// The charges deducted from a converted amount
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Charges {
+    // Protocol fee, paid to the fee collector
+    pub fee: u64,
+    // Spread, retained by the liquidity provider
+    pub spread: u64,
+    // Amount paid out to the receiver
+    pub net: u64,
+}
+
+impl FxConfig {
+    /// Split the [`gross`] converted amount into its charges, rounding them down
+    pub fn charges(&self, gross: u64) -> Charges {
+        let charge = |bps: u16| (gross as u128 * bps as u128 / BPS as u128) as u64;
+        let fee = charge(self.fee_bps);
+        let spread = charge(self.spread_bps);
+        Charges {
+            fee,
+            spread,
+            net: gross.saturating_sub(fee).saturating_sub(spread),
+        }
+    }
+}
+
+impl Sealed for FxConfig {}
+
+impl Pack for FxConfig {
+    const LEN: usize = size_of::<bool>() + 2 * size_of::<Pubkey>() + 2 * size_of::<u16>();
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, FxConfig::LEN];
+        let (is_initialized, admin, fee_bps, spread_bps, fee_collector) = mut_array_refs![
+            dst,
+            size_of::<bool>(),
+            size_of::<Pubkey>(),
+            size_of::<u16>(),
+            size_of::<u16>(),
+            size_of::<Pubkey>()
+        ];
+
+        is_initialized[0] = self.is_initialized as u8;
+        admin.copy_from_slice(self.admin.as_ref());
+        *fee_bps = self.fee_bps.to_be_bytes();
+        *spread_bps = self.spread_bps.to_be_bytes();
+        fee_collector.copy_from_slice(self.fee_collector.as_ref());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, FxConfig::LEN];
+        let (is_initialized, admin, fee_bps, spread_bps, fee_collector) = array_refs![
+            src,
+            size_of::<bool>(),
+            size_of::<Pubkey>(),
+            size_of::<u16>(),
+            size_of::<u16>(),
+            size_of::<Pubkey>()
+        ];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(Self {
+            is_initialized,
+            admin: Pubkey::from(*admin),
+            fee_bps: u16::from_be_bytes(*fee_bps),
+            spread_bps: u16::from_be_bytes(*spread_bps),
+            fee_collector: Pubkey::from(*fee_collector),
+        })
+    }
+}
+
+impl IsInitialized for FxConfig {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Administered for FxConfig {
+    fn new(admin: Pubkey) -> Self {
+        FxConfig {
+            is_initialized: true,
+            admin,
+            ..FxConfig::default()
+        }
+    }
+
+    fn admin(&self) -> &Pubkey {
+        &self.admin
+    }
+}
+
+/// Record of a swap's executions, kept once its FX account is closed.
+/// It belongs to a single swap: later swaps reusing the FX account can't record a receipt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SettlementReceipt {
+    pub is_initialized: bool,
+    pub initializer: Pubkey,
+    pub from_holding: Pubkey,
+    pub to_holding: Pubkey,
+    // Escrowed amount settled so far, in the initiated token
+    pub amount_in: u64,
+    // Amount paid to the [`to_holding`] so far, net of the fee & spread
+    pub amount_out: u64,
+    // Rate, feed rounds, trigger & time of the latest execution
+    pub rate: Decimal,
+    pub round_id: u64,
+    pub cross_round_id: Option<u64>,
+    pub reason: Trigger,
+    pub timestamp: UnixTimestamp,
+}
+
+impl Sealed for SettlementReceipt {}
+
+impl Pack for SettlementReceipt {
+    const LEN: usize = size_of::<bool>()
+        + 3 * size_of::<Pubkey>()
+        + 2 * size_of::<u64>()
+        + size_of::<Decimal>()
+        + size_of::<u64>()
+        + OPTIONAL_U64_LEN
+        + size_of::<u8>()
+        + size_of::<UnixTimestamp>();
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, SettlementReceipt::LEN];
+        let (
+            is_initialized,
+            initializer,
+            from_holding,
+            to_holding,
+            amount_in,
+            amount_out,
+            rate,
+            round_id,
+            cross_round_id,
+            reason,
+            timestamp,
+        ) = mut_array_refs![
+            dst,
+            size_of::<bool>(),
+            size_of::<Pubkey>(),
+            size_of::<Pubkey>(),
+            size_of::<Pubkey>(),
+            size_of::<u64>(),
+            size_of::<u64>(),
+            size_of::<Decimal>(),
+            size_of::<u64>(),
+            OPTIONAL_U64_LEN,
+            size_of::<u8>(),
+            size_of::<UnixTimestamp>()
+        ];
+
+        is_initialized[0] = self.is_initialized as u8;
+        initializer.copy_from_slice(self.initializer.as_ref());
+        from_holding.copy_from_slice(self.from_holding.as_ref());
+        to_holding.copy_from_slice(self.to_holding.as_ref());
+        *amount_in = self.amount_in.to_be_bytes();
+        *amount_out = self.amount_out.to_be_bytes();
+        rate.copy_from_slice(&self.rate.serialize());
+        *round_id = self.round_id.to_be_bytes();
+        pack_optional_u64(&self.cross_round_id, cross_round_id);
+        reason[0] = self.reason.into();
+        *timestamp = self.timestamp.to_be_bytes();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, SettlementReceipt::LEN];
+        let (
+            is_initialized,
+            initializer,
+            from_holding,
+            to_holding,
+            amount_in,
+            amount_out,
+            rate,
+            round_id,
+            cross_round_id,
+            reason,
+            timestamp,
+        ) = array_refs![
+            src,
+            size_of::<bool>(),
+            size_of::<Pubkey>(),
+            size_of::<Pubkey>(),
+            size_of::<Pubkey>(),
+            size_of::<u64>(),
+            size_of::<u64>(),
+            size_of::<Decimal>(),
+            size_of::<u64>(),
+            OPTIONAL_U64_LEN,
+            size_of::<u8>(),
+            size_of::<UnixTimestamp>()
+        ];
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        Ok(Self {
+            is_initialized,
+            initializer: Pubkey::from(*initializer),
+            from_holding: Pubkey::from(*from_holding),
+            to_holding: Pubkey::from(*to_holding),
+            amount_in: u64::from_be_bytes(*amount_in),
+            amount_out: u64::from_be_bytes(*amount_out),
+            rate: Decimal::deserialize(*rate),
+            round_id: u64::from_be_bytes(*round_id),
+            cross_round_id: unpack_optional_u64(cross_round_id)?,
+            reason: Trigger::try_from(reason[0])?,
+            timestamp: UnixTimestamp::from_be_bytes(*timestamp),
+        })
+    }
+}
+
+impl IsInitialized for SettlementReceipt {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+/// Absent keys are packed as the default key
+fn pack_optional_key(key: &Option<Pubkey>, dst: &mut [u8; 32]) {
+    dst.copy_from_slice(key.unwrap_or_default().as_ref());
+}
+
+fn unpack_optional_key(src: &[u8; 32]) -> Option<Pubkey> {
+    let key = Pubkey::from(*src);
+    (key != Pubkey::default()).then_some(key)
+}
+
+/// Optional numbers are packed as a presence flag followed by the number
+const OPTIONAL_U64_LEN: usize = size_of::<bool>() + size_of::<u64>();
+
+fn pack_optional_u64(value: &Option<u64>, dst: &mut [u8; OPTIONAL_U64_LEN]) {
+    let (is_some, value_dst) = mut_array_refs![dst, size_of::<bool>(), size_of::<u64>()];
+    is_some[0] = value.is_some() as u8;
+    *value_dst = value.unwrap_or_default().to_be_bytes();
+}
+
+fn unpack_optional_u64(src: &[u8; OPTIONAL_U64_LEN]) -> Result<Option<u64>, ProgramError> {
+    let (is_some, value) = array_refs![src, size_of::<bool>(), size_of::<u64>()];
+    match is_some {
+        [0] => Ok(None),
+        [1] => Ok(Some(u64::from_be_bytes(*value))),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
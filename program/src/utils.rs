@@ -1,8 +1,92 @@
+use crate::error::FxError;
+use solana_program::account_info::AccountInfo;
+use solana_program::bpf_loader_upgradeable;
+use solana_program::entrypoint::ProgramResult;
 use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
 
+/// FX account of the [`initializer`]'s swap with the given [`nonce`].
+/// A closed swap's nonce derives the same FX account, vault & receipt again, so nonces are never reused.
 #[inline]
-pub fn pda_swap() -> (Pubkey, u8) {
-    Pubkey::find_program_address(&[PDA_SEED], &crate::id())
+pub fn pda_fx_account(initializer: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[FX_ACCOUNT_SEED, initializer.as_ref(), &nonce.to_le_bytes()],
+        &crate::id(),
+    )
 }
 
-pub const PDA_SEED: &[u8] = b"m10fxswap";
+pub const FX_ACCOUNT_SEED: &[u8] = b"m10fxaccount";
+
+/// Escrow token account of the swap held in [`fx_account`], acting as its own authority
+#[inline]
+pub fn pda_vault(fx_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VAULT_SEED, fx_account.as_ref()], &crate::id())
+}
+
+pub const VAULT_SEED: &[u8] = b"m10fxvault";
+
+/// Settlement receipt of the swap held in [`fx_account`], kept once the swap is closed
+#[inline]
+pub fn pda_receipt(fx_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RECEIPT_SEED, fx_account.as_ref()], &crate::id())
+}
+
+pub const RECEIPT_SEED: &[u8] = b"m10fxreceipt";
+
+#[inline]
+pub fn pda_liquidity_registry() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[LIQUIDITY_REGISTRY_SEED], &crate::id())
+}
+
+pub const LIQUIDITY_REGISTRY_SEED: &[u8] = b"m10fxliquidity";
+
+#[inline]
+pub fn pda_feed_registry() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[FEED_REGISTRY_SEED], &crate::id())
+}
+
+pub const FEED_REGISTRY_SEED: &[u8] = b"m10fxfeeds";
+
+#[inline]
+pub fn pda_config() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONFIG_SEED], &crate::id())
+}
+
+pub const CONFIG_SEED: &[u8] = b"m10fxconfig";
+
+/// Delegate approved by the [`liquidity`] provider to pay out its swaps executed by anyone.
+/// Each provider has its own, so the program can only ever pay out the provider's own swaps under its approval.
+#[inline]
+pub fn pda_liquidity_authority(liquidity: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[LIQUIDITY_AUTHORITY_SEED, liquidity.as_ref()],
+        &crate::id(),
+    )
+}
+
+pub const LIQUIDITY_AUTHORITY_SEED: &[u8] = b"m10fxauthority";
+
+/// ProgramData account of the program deployed by the upgradeable loader, recording its upgrade authority
+#[inline]
+pub fn pda_program_data() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[crate::id().as_ref()], &bpf_loader_upgradeable::id())
+}
+
+/// Close the program owned [`account`], moving all of its lamports to [`destination`].
+/// Its data is zeroed & the account is handed back to the system program,
+/// so it can't be revived as a program account later in the same transaction.
+/// No closed discriminator is written, as the runtime only reassigns accounts without data:
+/// the system program owning the account marks it as closed to the program's owner checks.
+pub fn close_program_account(account: &AccountInfo, destination: &AccountInfo) -> ProgramResult {
+    **destination.lamports.borrow_mut() = destination
+        .lamports()
+        .checked_add(account.lamports())
+        .ok_or(FxError::InvalidAmount)?;
+    **account.lamports.borrow_mut() = 0;
+
+    // The runtime only allows reassigning zeroed data
+    account.try_borrow_mut_data()?.fill(0);
+    account.realloc(0, false)?;
+    account.assign(&system_program::id());
+    Ok(())
+}
@@ -0,0 +1,77 @@
+//! Accounts of the tokens, upgradeable loader & Pyth oracle, stored directly in the bank
+use solana_program::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_program::clock::UnixTimestamp;
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_sdk::account::Account;
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+/// An initialized mint of [`decimals`] decimals
+pub fn mint(decimals: u8) -> Account {
+    let mut account = Account::new(
+        Rent::default().minimum_balance(Mint::LEN),
+        Mint::LEN,
+        &spl_token::id(),
+    );
+    let mint = Mint {
+        mint_authority: COption::Some(Pubkey::new_unique()),
+        supply: u64::MAX / 2,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    Mint::pack(mint, &mut account.data).expect("Could not pack mint");
+    account
+}
+
+pub fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut account = Account::new(
+        Rent::default().minimum_balance(TokenAccount::LEN),
+        TokenAccount::LEN,
+        &spl_token::id(),
+    );
+    let token = TokenAccount {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    };
+    TokenAccount::pack(token, &mut account.data).expect("Could not pack token account");
+    account
+}
+
+/// A ProgramData account of the upgradeable loader, recording the [`upgrade_authority`]
+pub fn program_data(upgrade_authority: &Pubkey) -> Account {
+    let state = UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: Some(*upgrade_authority),
+    };
+    let len = UpgradeableLoaderState::size_of_programdata_metadata();
+    Account::new_data_with_space(
+        Rent::default().minimum_balance(len),
+        &state,
+        len,
+        &bpf_loader_upgradeable::id(),
+    )
+    .expect("Could not serialize program data")
+}
+
+/// Length of the Pyth price account header read by the program
+pub const PYTH_PRICE_LEN: usize = 240;
+
+/// A trading Pyth price account published in [`slot`], without a confidence interval
+pub fn pyth_price(price: i64, exponent: i32, timestamp: UnixTimestamp, slot: u64) -> Vec<u8> {
+    let mut data = vec![0; PYTH_PRICE_LEN];
+    data[0..4].copy_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+    data[4..8].copy_from_slice(&2u32.to_le_bytes());
+    data[8..12].copy_from_slice(&3u32.to_le_bytes());
+    data[20..24].copy_from_slice(&exponent.to_le_bytes());
+    data[96..104].copy_from_slice(&timestamp.to_le_bytes());
+    data[208..216].copy_from_slice(&price.to_le_bytes());
+    data[224..228].copy_from_slice(&1u32.to_le_bytes());
+    data[232..240].copy_from_slice(&slot.to_le_bytes());
+    data
+}
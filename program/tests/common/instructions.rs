@@ -0,0 +1,38 @@
+//! Patch the accounts of instructions by their key, so tests don't depend on the account order
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey::Pubkey;
+
+pub trait PatchAccounts {
+    /// Pass [`replacement`] instead of the [`account`]
+    fn replace_account(&mut self, account: &Pubkey, replacement: Pubkey);
+
+    /// Pass the [`account`] without its signature
+    fn unsign(&mut self, account: &Pubkey);
+}
+
+impl PatchAccounts for Instruction {
+    fn replace_account(&mut self, account: &Pubkey, replacement: Pubkey) {
+        account_meta(self, account).pubkey = replacement;
+    }
+
+    fn unsign(&mut self, account: &Pubkey) {
+        account_meta(self, account).is_signer = false;
+    }
+}
+
+/// The only account of the instruction with the given key
+fn account_meta<'a>(ix: &'a mut Instruction, account: &Pubkey) -> &'a mut AccountMeta {
+    let mut matches = ix
+        .accounts
+        .iter_mut()
+        .filter(|meta| meta.pubkey == *account);
+    let meta = matches
+        .next()
+        .unwrap_or_else(|| panic!("{} isn't passed to the instruction", account));
+    assert!(
+        matches.next().is_none(),
+        "{} is passed more than once",
+        account
+    );
+    meta
+}
@@ -0,0 +1,92 @@
+use m10_fx_solana::events::PROGRAM_DATA_PREFIX;
+use solana_program::account_info::AccountInfo;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::instruction::Instruction;
+use solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use solana_program::pubkey::Pubkey;
+use std::sync::{Once, OnceLock};
+use std::thread::yield_now;
+
+/// The native processor drops the fields of `sol_log_data`, so log them as the runtime does.
+/// They're written through `sol_log`, whose prefix [`TestEnv::events`] strips.
+pub struct LogDataStubs;
+
+/// The stubs installed by the first started bank
+static BANK_STUBS: OnceLock<Box<dyn SyscallStubs>> = OnceLock::new();
+
+impl LogDataStubs {
+    pub const PREFIX: &'static str = "Program log: ";
+
+    /// Wrap the stubs installed by the first started bank
+    pub fn install() {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            let stubs = set_syscall_stubs(Box::new(LogDataStubs));
+            let _ = BANK_STUBS.set(stubs);
+        });
+    }
+
+    /// Programs of other banks may call in before the wrapped stubs are stored
+    fn inner() -> &'static dyn SyscallStubs {
+        loop {
+            if let Some(stubs) = BANK_STUBS.get() {
+                return stubs.as_ref();
+            }
+            yield_now();
+        }
+    }
+}
+
+impl SyscallStubs for LogDataStubs {
+    fn sol_log(&self, message: &str) {
+        Self::inner().sol_log(message)
+    }
+    fn sol_log_compute_units(&self) {
+        Self::inner().sol_log_compute_units()
+    }
+    fn sol_remaining_compute_units(&self) -> u64 {
+        Self::inner().sol_remaining_compute_units()
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        Self::inner().sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        Self::inner().sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        Self::inner().sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        Self::inner().sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        Self::inner().sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        Self::inner().sol_get_epoch_rewards_sysvar(var_addr)
+    }
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        Self::inner().sol_get_last_restart_slot(var_addr)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        Self::inner().sol_get_return_data()
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        Self::inner().sol_set_return_data(data)
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let fields: Vec<_> = fields.iter().map(base64::encode).collect();
+        Self::inner().sol_log(&format!("{}{}", PROGRAM_DATA_PREFIX, fields.join(" ")))
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        Self::inner().sol_get_processed_sibling_instruction(index)
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        Self::inner().sol_get_stack_height()
+    }
+}
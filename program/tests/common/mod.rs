@@ -0,0 +1,418 @@
+//! Test harness running the FX swap program on a `solana-program-test` bank.
+//! It sets up mints, self-custodied liquidity accounts, the program registries & fake FX feeds.
+#![allow(dead_code)]
+
+mod accounts;
+mod instructions;
+mod log_data;
+mod swaps;
+
+pub use self::accounts::{program_data, token_account};
+// Not every test uses them
+#[allow(unused_imports)]
+pub use self::{instructions::PatchAccounts, swaps::Swap};
+
+use self::accounts::{mint, pyth_price, PYTH_PRICE_LEN};
+use self::log_data::LogDataStubs;
+use m10_fx_solana::error::FxError;
+use m10_fx_solana::events::FxLog;
+use m10_fx_solana::instruction;
+use m10_fx_solana::processor::FxSwap;
+use m10_fx_solana::rates::{Oracle, USD_TO_EUR};
+use m10_fx_solana::state::{FxData, FxFeed};
+use m10_fx_solana::utils::{pda_liquidity_authority, pda_program_data};
+use rust_decimal::Decimal;
+use solana_program::clock::{Clock, UnixTimestamp};
+use solana_program::instruction::{Instruction, InstructionError};
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::system_program;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use spl_token::state::Account as TokenAccount;
+use std::time::Duration;
+
+pub const DECIMALS: u8 = 2;
+pub const LIQUIDITY: u64 = 100_000_000;
+/// Exchange rate of the static USD -> EUR feed
+pub const STATIC_RATE: Decimal = Decimal::from_parts(9, 0, 0, false, 1);
+/// Maximum age of the rates of registered feeds, in seconds
+pub const MAX_RATE_AGE: u64 = 60;
+
+/// A token with a registered liquidity provider
+pub struct Currency {
+    pub mint: Pubkey,
+    // Token account acting as its own authority, as expected by the program
+    pub liquidity: Keypair,
+}
+
+pub struct TestEnv {
+    pub context: ProgramTestContext,
+    pub admin: Keypair,
+    pub usd: Currency,
+    pub eur: Currency,
+    // Feed quoting USD -> EUR, once registered
+    pub feed: Option<FxFeed>,
+    // Feed quoting the second leg of a USD -> base -> EUR cross rate, once registered
+    pub cross_feed: Option<FxFeed>,
+    pub oracle: Oracle,
+    // EUR account collecting the protocol fee, once configured
+    pub fee_account: Option<Pubkey>,
+}
+
+impl TestEnv {
+    /// Start a bank with USD & EUR liquidity registered, but no FX feeds
+    pub async fn start() -> Self {
+        let mut env = Self::empty().await;
+        for liquidity in [env.usd.liquidity.pubkey(), env.eur.liquidity.pubkey()] {
+            let ix = instruction::register_liquidity(env.admin.pubkey(), liquidity);
+            process(&mut env.context, &[ix], &[&env.admin])
+                .await
+                .expect("Could not register liquidity");
+        }
+        env
+    }
+
+    /// Start a bank with the USD & EUR tokens, but none of the program's registries.
+    /// The [`admin`] is the program's upgrade authority.
+    pub async fn empty() -> Self {
+        let mut program_test = ProgramTest::new(
+            "m10_fx_solana",
+            m10_fx_solana::id(),
+            processor!(FxSwap::process),
+        );
+        let admin = Keypair::new();
+        program_test.add_account(
+            admin.pubkey(),
+            Account::new(100_000_000_000, 0, &system_program::id()),
+        );
+        program_test.add_account(pda_program_data().0, program_data(&admin.pubkey()));
+        let usd = Currency::add(&mut program_test);
+        let eur = Currency::add(&mut program_test);
+
+        let context = program_test.start_with_context().await;
+        LogDataStubs::install();
+        TestEnv {
+            context,
+            admin,
+            usd,
+            eur,
+            feed: None,
+            cross_feed: None,
+            oracle: Oracle::Demo,
+            fee_account: None,
+        }
+    }
+
+    /// Start a bank quoting USD -> EUR through the static demo feed
+    pub async fn with_static_feed() -> Self {
+        let mut env = Self::start().await;
+        let feed = FxFeed {
+            from_mint: env.usd.mint,
+            to_mint: env.eur.mint,
+            feed: USD_TO_EUR,
+            oracle_program: Pubkey::new_unique(),
+            oracle: Oracle::Demo,
+            inverted: false,
+            max_age: MAX_RATE_AGE,
+        };
+        env.add_feed(feed).await;
+        env
+    }
+
+    /// Start a bank quoting USD -> EUR through a fake Pyth price account
+    pub async fn with_pyth_feed(price: i64, exponent: i32) -> Self {
+        let mut env = Self::start().await;
+        let feed = FxFeed {
+            from_mint: env.usd.mint,
+            to_mint: env.eur.mint,
+            feed: Pubkey::new_unique(),
+            oracle_program: Pubkey::new_unique(),
+            oracle: Oracle::Pyth,
+            inverted: false,
+            max_age: MAX_RATE_AGE,
+        };
+        env.add_feed(feed).await;
+        env.set_rate(price, exponent).await;
+        env
+    }
+
+    /// Start a bank quoting USD -> EUR at the cross rate through a base currency, without a direct feed.
+    /// Both legs are quoted by fake Pyth price accounts, registered as USD -> base & base -> EUR,
+    /// or as the inverse currency pair if [`first_inverted`] or [`second_inverted`] resp.
+    pub async fn with_cross_feeds(first_inverted: bool, second_inverted: bool) -> Self {
+        let mut env = Self::start().await;
+        let base_mint = env.mint();
+        let oracle_program = Pubkey::new_unique();
+        let leg = |from_mint, to_mint, inverted| {
+            let (from_mint, to_mint) = if inverted {
+                (to_mint, from_mint)
+            } else {
+                (from_mint, to_mint)
+            };
+            FxFeed {
+                from_mint,
+                to_mint,
+                feed: Pubkey::new_unique(),
+                oracle_program,
+                oracle: Oracle::Pyth,
+                inverted: false,
+                max_age: MAX_RATE_AGE,
+            }
+        };
+        let second = leg(base_mint, env.eur.mint, second_inverted);
+        env.add_feed(second).await;
+        env.add_feed(leg(env.usd.mint, base_mint, first_inverted))
+            .await;
+        env.cross_feed = Some(second);
+
+        let ix = instruction::set_base_mint(env.admin.pubkey(), Some(base_mint));
+        process(&mut env.context, &[ix], &[&env.admin])
+            .await
+            .expect("Could not set the base mint");
+        env
+    }
+
+    async fn add_feed(&mut self, feed: FxFeed) {
+        let ix = instruction::add_fx_feed(self.admin.pubkey(), feed);
+        process(&mut self.context, &[ix], &[&self.admin])
+            .await
+            .expect("Could not add FX feed");
+        self.feed = Some(feed);
+        self.oracle = feed.oracle;
+    }
+
+    pub fn feed(&self) -> FxFeed {
+        self.feed.expect("No FX feed registered")
+    }
+
+    /// Publish a price on the fake Pyth feed
+    pub fn set_pyth_price(&mut self, price: i64, exponent: i32, timestamp: UnixTimestamp) {
+        self.publish_price(&self.feed(), price, exponent, timestamp, 0);
+    }
+
+    /// Publish a price on the fake Pyth [`feed`] in [`slot`]
+    pub fn publish_price(
+        &mut self,
+        feed: &FxFeed,
+        price: i64,
+        exponent: i32,
+        timestamp: UnixTimestamp,
+        slot: u64,
+    ) {
+        let mut account = Account::new(
+            Rent::default().minimum_balance(PYTH_PRICE_LEN),
+            PYTH_PRICE_LEN,
+            &feed.oracle_program,
+        );
+        account.data = pyth_price(price, exponent, timestamp, slot);
+        self.context.set_account(&feed.feed, &account.into());
+    }
+
+    /// Publish a price on the fake Pyth feed at the current time
+    pub async fn set_rate(&mut self, price: i64, exponent: i32) {
+        let now = self.now().await;
+        self.set_pyth_price(price, exponent, now);
+    }
+
+    /// Sign & submit the instructions, paid by the context's payer
+    pub async fn process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        process(&mut self.context, instructions, signers).await
+    }
+
+    /// Sign & submit the instructions, returning the events they logged, even if they failed
+    pub async fn events(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Vec<FxLog> {
+        let tx = transaction(&mut self.context, instructions, signers).await;
+        let metadata = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(tx)
+            .await
+            .expect("Could not process transaction")
+            .metadata
+            .expect("Missing transaction metadata");
+        let messages: Vec<_> = metadata
+            .log_messages
+            .iter()
+            .map(|message| message.trim_start_matches(LogDataStubs::PREFIX))
+            .collect();
+        FxLog::from_log_messages(&messages)
+    }
+
+    pub async fn now(&mut self) -> UnixTimestamp {
+        self.clock().await.unix_timestamp
+    }
+
+    /// Move the cluster time forward
+    pub async fn warp(&mut self, duration: Duration) {
+        let mut clock = self.clock().await;
+        clock.unix_timestamp += duration.as_secs() as UnixTimestamp;
+        self.context.set_sysvar(&clock);
+    }
+
+    async fn clock(&mut self) -> Clock {
+        self.context
+            .banks_client
+            .get_sysvar::<Clock>()
+            .await
+            .expect("Could not get clock")
+    }
+
+    pub async fn account(&mut self, key: &Pubkey) -> Option<Account> {
+        self.context
+            .banks_client
+            .get_account(*key)
+            .await
+            .expect("Could not get account")
+    }
+
+    pub async fn balance(&mut self, token_account: &Pubkey) -> u64 {
+        let account = self
+            .account(token_account)
+            .await
+            .expect("Missing token account");
+        TokenAccount::unpack(&account.data)
+            .expect("Invalid token account")
+            .amount
+    }
+
+    pub async fn fx_data(&mut self, fx_account: &Pubkey) -> FxData {
+        let account = self.account(fx_account).await.expect("Missing FX account");
+        FxData::unpack(&account.data).expect("Invalid FX data")
+    }
+
+    /// Store [`account`] at [`key`], bypassing the program
+    pub fn set_account(&mut self, key: &Pubkey, account: Account) {
+        self.context.set_account(key, &account.into());
+    }
+
+    /// Create a token account holding [`amount`] tokens
+    pub fn token_account(&mut self, mint: &Pubkey, owner: &Pubkey, amount: u64) -> Pubkey {
+        let key = Pubkey::new_unique();
+        self.set_account(&key, token_account(mint, owner, amount));
+        key
+    }
+
+    /// Create a mint without a liquidity provider
+    pub fn mint(&mut self) -> Pubkey {
+        let key = Pubkey::new_unique();
+        self.set_account(&key, mint(DECIMALS));
+        key
+    }
+
+    /// Change the decimals of the [`mint`], leaving the token accounts as they are
+    pub fn set_decimals(&mut self, mint: &Pubkey, decimals: u8) {
+        self.set_account(mint, self::mint(decimals));
+    }
+
+    /// Approve the program to pay out up to [`amount`] from the EUR liquidity provider
+    pub async fn approve_delegate(&mut self, amount: u64) {
+        let liquidity = self.eur.liquidity.pubkey();
+        let ix = spl_token::instruction::approve(
+            &spl_token::id(),
+            &liquidity,
+            &pda_liquidity_authority(&liquidity).0,
+            &liquidity,
+            &[],
+            amount,
+        )
+        .unwrap();
+        process(&mut self.context, &[ix], &[&self.eur.liquidity])
+            .await
+            .expect("Could not approve the delegate");
+    }
+
+    /// Register an additional liquidity provider for [`mint`]
+    pub async fn add_liquidity(&mut self, mint: &Pubkey) -> Keypair {
+        let liquidity = Keypair::new();
+        self.set_account(
+            &liquidity.pubkey(),
+            token_account(mint, &liquidity.pubkey(), LIQUIDITY),
+        );
+        let ix = instruction::register_liquidity(self.admin.pubkey(), liquidity.pubkey());
+        process(&mut self.context, &[ix], &[&self.admin])
+            .await
+            .expect("Could not register liquidity");
+        liquidity
+    }
+
+    /// Charge a protocol fee & spread, collecting the fee in a fresh EUR account
+    pub async fn set_fees(
+        &mut self,
+        fee_bps: u16,
+        spread_bps: u16,
+    ) -> Result<(), BanksClientError> {
+        let fee_collector = Pubkey::new_unique();
+        let eur_mint = self.eur.mint;
+        self.fee_account = Some(self.token_account(&eur_mint, &fee_collector, 0));
+        let ix = instruction::set_fees(self.admin.pubkey(), fee_bps, spread_bps, fee_collector);
+        process(&mut self.context, &[ix], &[&self.admin]).await
+    }
+}
+
+/// Sign & submit the instructions, paid by the context's payer
+async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let tx = transaction(context, instructions, signers).await;
+    context.banks_client.process_transaction(tx).await
+}
+
+/// Sign the instructions, paid by the context's payer
+async fn transaction(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Transaction {
+    // Identical transactions need a fresh blockhash to be processed again
+    let blockhash = context
+        .get_new_latest_blockhash()
+        .await
+        .expect("Could not get blockhash");
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    )
+}
+
+impl Currency {
+    fn add(program_test: &mut ProgramTest) -> Self {
+        let mint = Pubkey::new_unique();
+        program_test.add_account(mint, self::mint(DECIMALS));
+        let liquidity = Keypair::new();
+        program_test.add_account(
+            liquidity.pubkey(),
+            token_account(&mint, &liquidity.pubkey(), LIQUIDITY),
+        );
+        Currency { mint, liquidity }
+    }
+}
+
+/// Assert the transaction failed with [`error`]
+pub fn assert_fx_error(result: Result<(), BanksClientError>, error: FxError) {
+    let code = error as u32;
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(actual),
+        ))) if actual == code => {}
+        other => panic!("Expected FxError {}, got {:?}", code, other),
+    }
+}
@@ -0,0 +1,206 @@
+//! Swaps of USD -> EUR & the instructions initiating, executing & closing them
+use super::{process, token_account, TestEnv};
+use m10_fx_solana::instruction;
+use m10_fx_solana::state::{ExpiryPolicy, SwapKind};
+use m10_fx_solana::utils::{pda_fx_account, pda_receipt, pda_vault};
+use rust_decimal::Decimal;
+use solana_program::instruction::Instruction;
+use solana_program::pubkey::Pubkey;
+use solana_program_test::BanksClientError;
+use solana_sdk::signature::{Keypair, Signer};
+use std::ops::Range;
+use std::time::Duration;
+
+/// An FX swap of USD -> EUR, with its accounts allocated but not yet initiated
+pub struct Swap {
+    pub initializer: Keypair,
+    pub holding: Keypair,
+    pub nonce: u64,
+    pub fx_account: Pubkey,
+    pub to_account: Pubkey,
+    pub refund_account: Pubkey,
+    pub amount: u64,
+    pub kind: SwapKind,
+    pub expiry: ExpiryPolicy,
+    pub crank_reward: u64,
+    pub receipt: bool,
+}
+
+impl Swap {
+    /// The escrow vault holding the unfilled amount
+    pub fn vault(&self) -> Pubkey {
+        pda_vault(&self.fx_account).0
+    }
+
+    /// The settlement receipt, if requested
+    pub fn receipt(&self) -> Pubkey {
+        pda_receipt(&self.fx_account).0
+    }
+}
+
+impl TestEnv {
+    /// Allocate the accounts of a USD -> EUR swap of [`amount`] cents, funding the holding account.
+    /// The swap is one-cancels-other & settles at market on expiry.
+    pub fn swap(&mut self, amount: u64) -> Swap {
+        self.swap_with_nonce(Keypair::new(), 0, amount)
+    }
+
+    /// Allocate the accounts of the [`initializer`]'s swap with the given [`nonce`]
+    pub fn swap_with_nonce(&mut self, initializer: Keypair, nonce: u64, amount: u64) -> Swap {
+        let (fx_account, _) = pda_fx_account(&initializer.pubkey(), nonce);
+        let holding = Keypair::new();
+        self.set_account(
+            &holding.pubkey(),
+            token_account(&self.usd.mint, &pda_vault(&fx_account).0, amount),
+        );
+        let eur_mint = self.eur.mint;
+        let usd_mint = self.usd.mint;
+        Swap {
+            to_account: self.token_account(&eur_mint, &initializer.pubkey(), 0),
+            refund_account: self.token_account(&usd_mint, &initializer.pubkey(), 0),
+            initializer,
+            holding,
+            nonce,
+            fx_account,
+            amount,
+            kind: SwapKind::Oco,
+            expiry: ExpiryPolicy::SettleAtMarket,
+            crank_reward: 0,
+            receipt: false,
+        }
+    }
+
+    pub fn initiate_ix(
+        &self,
+        swap: &Swap,
+        limits: Range<Decimal>,
+        valid_for: Duration,
+    ) -> Instruction {
+        let feed = self.feed();
+        instruction::initiate(
+            swap.initializer.pubkey(),
+            self.context.payer.pubkey(),
+            swap.holding.pubkey(),
+            swap.to_account,
+            swap.refund_account,
+            self.usd.mint,
+            self.eur.mint,
+            swap.nonce,
+            feed.feed,
+            self.cross_feed.map(|feed| feed.feed),
+            self.usd.liquidity.pubkey(),
+            swap.amount,
+            limits.end,
+            limits.start,
+            Some(valid_for),
+            self.oracle,
+            swap.kind,
+            swap.expiry,
+            swap.crank_reward,
+            swap.receipt,
+        )
+    }
+
+    pub async fn initiate(
+        &mut self,
+        swap: &Swap,
+        limits: Range<Decimal>,
+        valid_for: Duration,
+    ) -> Result<(), BanksClientError> {
+        let ix = self.initiate_ix(swap, limits, valid_for);
+        self.process(&[ix], &[&swap.holding, &swap.initializer])
+            .await
+    }
+
+    pub fn execute_ix(
+        &self,
+        swap: &Swap,
+        liquidity: &Pubkey,
+        max_amount: Option<u64>,
+    ) -> Instruction {
+        self.crank_ix(
+            swap,
+            &self.context.payer.pubkey(),
+            liquidity,
+            false,
+            max_amount,
+        )
+    }
+
+    /// Execute the [`swap`] on behalf of the [`cranker`], either signed by the [`liquidity`] provider or its delegate
+    pub fn crank_ix(
+        &self,
+        swap: &Swap,
+        cranker: &Pubkey,
+        liquidity: &Pubkey,
+        delegated: bool,
+        max_amount: Option<u64>,
+    ) -> Instruction {
+        let feed = self.feed();
+        instruction::execute(
+            *cranker,
+            swap.initializer.pubkey(),
+            swap.to_account,
+            *liquidity,
+            delegated,
+            self.usd.liquidity.pubkey(),
+            swap.fx_account,
+            feed.feed,
+            feed.oracle_program,
+            self.cross_feed.map(|feed| feed.feed),
+            self.fee_account,
+            max_amount,
+        )
+    }
+
+    /// Settle the remaining amount from the EUR liquidity provider
+    pub async fn execute(&mut self, swap: &Swap) -> Result<(), BanksClientError> {
+        let ix = self.execute_ix(swap, &self.eur.liquidity.pubkey(), None);
+        process(&mut self.context, &[ix], &[&self.eur.liquidity]).await
+    }
+
+    /// Settle at most [`max_amount`] from the [`liquidity`] provider
+    pub async fn fill(
+        &mut self,
+        swap: &Swap,
+        liquidity: &Keypair,
+        max_amount: u64,
+    ) -> Result<(), BanksClientError> {
+        let ix = self.execute_ix(swap, &liquidity.pubkey(), Some(max_amount));
+        process(&mut self.context, &[ix], &[liquidity]).await
+    }
+
+    /// Settle the remaining amount by the [`cranker`], paid out through the EUR provider's delegate
+    pub async fn crank(&mut self, swap: &Swap, cranker: &Keypair) -> Result<(), BanksClientError> {
+        let ix = self.crank_ix(
+            swap,
+            &cranker.pubkey(),
+            &self.eur.liquidity.pubkey(),
+            true,
+            None,
+        );
+        process(&mut self.context, &[ix], &[cranker]).await
+    }
+
+    pub fn cancel_ix(&self, swap: &Swap) -> Instruction {
+        instruction::cancel(
+            swap.initializer.pubkey(),
+            swap.refund_account,
+            swap.fx_account,
+        )
+    }
+
+    pub async fn cancel(&mut self, swap: &Swap) -> Result<(), BanksClientError> {
+        let ix = self.cancel_ix(swap);
+        process(&mut self.context, &[ix], &[&swap.initializer]).await
+    }
+
+    pub async fn refund(&mut self, swap: &Swap) -> Result<(), BanksClientError> {
+        let ix = instruction::refund(
+            swap.initializer.pubkey(),
+            swap.refund_account,
+            swap.fx_account,
+        );
+        self.process(&[ix], &[]).await
+    }
+}
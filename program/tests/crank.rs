@@ -0,0 +1,153 @@
+mod common;
+
+use common::{assert_fx_error, PatchAccounts, Swap, TestEnv, LIQUIDITY};
+use m10_fx_solana::error::FxError;
+use m10_fx_solana::utils::pda_liquidity_authority;
+use rust_decimal::Decimal;
+use solana_program::system_program;
+use solana_sdk::account::Account;
+use solana_sdk::signature::{Keypair, Signer};
+use std::ops::Range;
+use std::time::Duration;
+
+const AMOUNT: u64 = 10_000;
+const VALID_FOR: Duration = Duration::from_secs(300);
+const CRANK_REWARD: u64 = 5_000;
+/// Lamports funding the cranker's transactions
+const CRANKER_FUNDS: u64 = 1_000_000_000;
+
+/// Limits the static rate of 0.9 has already crossed
+fn limits() -> Range<Decimal> {
+    Decimal::new(5, 1)..Decimal::new(8, 1)
+}
+
+async fn initiate(env: &mut TestEnv) -> Swap {
+    let swap = Swap {
+        crank_reward: CRANK_REWARD,
+        ..env.swap(AMOUNT)
+    };
+    env.initiate(&swap, limits(), VALID_FOR).await.unwrap();
+    swap
+}
+
+fn cranker(env: &mut TestEnv) -> Keypair {
+    let cranker = Keypair::new();
+    env.set_account(
+        &cranker.pubkey(),
+        Account::new(CRANKER_FUNDS, 0, &system_program::id()),
+    );
+    cranker
+}
+
+#[tokio::test]
+async fn anyone_can_execute_through_the_delegate() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = initiate(&mut env).await;
+    assert_eq!(
+        env.fx_data(&swap.fx_account).await.crank_reward,
+        CRANK_REWARD
+    );
+
+    env.approve_delegate(AMOUNT).await;
+    let cranker = cranker(&mut env);
+    env.crank(&swap, &cranker).await.unwrap();
+
+    assert_eq!(env.balance(&swap.to_account).await, 9_000);
+    assert_eq!(
+        env.balance(&env.eur.liquidity.pubkey()).await,
+        LIQUIDITY - 9_000
+    );
+    assert!(env.account(&swap.fx_account).await.is_none());
+}
+
+#[tokio::test]
+async fn cranker_receives_the_reward() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = initiate(&mut env).await;
+    env.approve_delegate(AMOUNT).await;
+    let cranker = cranker(&mut env);
+    env.crank(&swap, &cranker).await.unwrap();
+
+    // The context's payer pays the transaction fees
+    let account = env.account(&cranker.pubkey()).await.unwrap();
+    assert_eq!(account.lamports, CRANKER_FUNDS + CRANK_REWARD);
+}
+
+#[tokio::test]
+async fn execution_without_a_delegate_is_rejected() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = initiate(&mut env).await;
+    let cranker = cranker(&mut env);
+    assert!(env.crank(&swap, &cranker).await.is_err());
+    assert_eq!(env.balance(&swap.to_account).await, 0);
+}
+
+#[tokio::test]
+async fn delegated_executions_fill_the_remaining_amount() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = initiate(&mut env).await;
+    env.approve_delegate(AMOUNT).await;
+    let cranker = cranker(&mut env);
+    let ix = env.crank_ix(
+        &swap,
+        &cranker.pubkey(),
+        &env.eur.liquidity.pubkey(),
+        true,
+        Some(1),
+    );
+    env.process(&[ix], &[&cranker]).await.unwrap();
+
+    assert_eq!(env.balance(&swap.to_account).await, 9_000);
+    assert!(env.account(&swap.fx_account).await.is_none());
+}
+
+#[tokio::test]
+async fn delegates_only_pay_out_their_own_provider() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = initiate(&mut env).await;
+    env.approve_delegate(AMOUNT).await;
+    let cranker = cranker(&mut env);
+    let mut ix = env.crank_ix(
+        &swap,
+        &cranker.pubkey(),
+        &env.eur.liquidity.pubkey(),
+        true,
+        None,
+    );
+    ix.replace_account(
+        &pda_liquidity_authority(&env.eur.liquidity.pubkey()).0,
+        pda_liquidity_authority(&env.usd.liquidity.pubkey()).0,
+    );
+    assert_fx_error(
+        env.process(&[ix], &[&cranker]).await,
+        FxError::InvalidRequest,
+    );
+}
+
+#[tokio::test]
+async fn cranker_needs_to_sign() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = initiate(&mut env).await;
+    env.approve_delegate(AMOUNT).await;
+    let cranker = cranker(&mut env);
+    let mut ix = env.crank_ix(
+        &swap,
+        &cranker.pubkey(),
+        &env.eur.liquidity.pubkey(),
+        true,
+        None,
+    );
+    ix.unsign(&cranker.pubkey());
+    assert_fx_error(env.process(&[ix], &[]).await, FxError::MissingSignature);
+}
+
+#[tokio::test]
+async fn cancel_returns_the_reward_to_the_initializer() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = initiate(&mut env).await;
+    let fx_account = env.account(&swap.fx_account).await.unwrap();
+    env.cancel(&swap).await.unwrap();
+
+    let initializer = env.account(&swap.initializer.pubkey()).await.unwrap();
+    assert!(initializer.lamports >= fx_account.lamports);
+}
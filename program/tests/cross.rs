@@ -0,0 +1,150 @@
+mod common;
+
+use common::{assert_fx_error, PatchAccounts, TestEnv, LIQUIDITY, MAX_RATE_AGE};
+use m10_fx_solana::error::FxError;
+use m10_fx_solana::instruction;
+use m10_fx_solana::state::FxFeed;
+use rust_decimal::Decimal;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::ops::Range;
+use std::time::Duration;
+
+const AMOUNT: u64 = 10_001;
+const VALID_FOR: Duration = Duration::from_secs(300);
+
+/// Limits any of the cross rates has already crossed
+fn crossed() -> Range<Decimal> {
+    Decimal::from(1_000)..Decimal::from(2_000)
+}
+
+/// Publish the Pyth prices of both legs at the current time
+async fn publish(env: &mut TestEnv, first: (i64, i32), second: (i64, i32)) {
+    let now = env.now().await;
+    let cross_feed = env.cross_feed.unwrap();
+    env.publish_price(&env.feed(), first.0, first.1, now, 1);
+    env.publish_price(&cross_feed, second.0, second.1, now, 1);
+}
+
+/// A bank quoting USD -> base at 0.2666 & EUR -> base at 0.000064, like SAR/USD & IDR/USD
+async fn sar_idr() -> TestEnv {
+    let mut env = TestEnv::with_cross_feeds(false, true).await;
+    publish(&mut env, (2_666, -4), (64, -6)).await;
+    env
+}
+
+#[tokio::test]
+async fn swaps_are_priced_through_the_base_currency() {
+    let mut env = sar_idr().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, crossed(), VALID_FOR).await.unwrap();
+
+    let fx_data = env.fx_data(&swap.fx_account).await;
+    assert_eq!(fx_data.fx_feed, env.feed().feed);
+    assert!(!fx_data.fx_feed_inverted);
+    assert_eq!(fx_data.cross_feed, Some(env.cross_feed.unwrap().feed));
+    assert!(fx_data.cross_feed_inverted);
+
+    // 100.01 * 0.2666 / 0.000064 = 416,604.15625, rounded down to the cent
+    env.execute(&swap).await.unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, 41_660_415);
+    assert_eq!(
+        env.balance(&env.eur.liquidity.pubkey()).await,
+        LIQUIDITY - 41_660_415
+    );
+    assert_eq!(
+        env.balance(&env.usd.liquidity.pubkey()).await,
+        LIQUIDITY + AMOUNT
+    );
+    assert!(env.account(&swap.fx_account).await.is_none());
+}
+
+#[tokio::test]
+async fn the_first_leg_is_inverted_when_quoted_against_the_base_currency() {
+    // base -> USD at 3.75 & base -> EUR at 15,625
+    let mut env = TestEnv::with_cross_feeds(true, false).await;
+    publish(&mut env, (375, -2), (15_625, 0)).await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, crossed(), VALID_FOR).await.unwrap();
+
+    let fx_data = env.fx_data(&swap.fx_account).await;
+    assert!(fx_data.fx_feed_inverted);
+    assert!(!fx_data.cross_feed_inverted);
+
+    // 100.01 / 3.75 * 15,625 = 416,708.33..., rounded down to the cent
+    env.execute(&swap).await.unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, 41_670_833);
+}
+
+#[tokio::test]
+async fn cross_rates_need_a_base_mint() {
+    let mut env = sar_idr().await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let ix = instruction::set_base_mint(admin.pubkey(), None);
+    env.process(&[ix], &[&admin]).await.unwrap();
+
+    let swap = env.swap(AMOUNT);
+    let result = env.initiate(&swap, crossed(), VALID_FOR).await;
+    assert_fx_error(result, FxError::InvalidFxFeed);
+}
+
+#[tokio::test]
+async fn initiate_without_the_second_feed_is_rejected() {
+    let mut env = sar_idr().await;
+    env.cross_feed = None;
+
+    let swap = env.swap(AMOUNT);
+    let result = env.initiate(&swap, crossed(), VALID_FOR).await;
+    assert_fx_error(result, FxError::InvalidFxFeed);
+}
+
+#[tokio::test]
+async fn execute_without_the_second_feed_is_rejected() {
+    let mut env = sar_idr().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, crossed(), VALID_FOR).await.unwrap();
+
+    let cross_feed = env.cross_feed.take();
+    assert_fx_error(env.execute(&swap).await, FxError::InvalidFxFeed);
+    assert_eq!(env.balance(&swap.to_account).await, 0);
+
+    env.cross_feed = cross_feed;
+    env.execute(&swap).await.unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, 41_660_415);
+}
+
+#[tokio::test]
+async fn execute_with_another_second_feed_is_rejected() {
+    let mut env = sar_idr().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, crossed(), VALID_FOR).await.unwrap();
+
+    // A feed of the same oracle, quoting a better rate
+    let cross_feed = env.cross_feed.unwrap();
+    let other = FxFeed {
+        feed: Pubkey::new_unique(),
+        ..cross_feed
+    };
+    let now = env.now().await;
+    env.publish_price(&other, 32, -6, now, 1);
+
+    let liquidity = Keypair::from_bytes(&env.eur.liquidity.to_bytes()).unwrap();
+    let mut ix = env.execute_ix(&swap, &liquidity.pubkey(), None);
+    ix.replace_account(&cross_feed.feed, other.feed);
+    let result = env.process(&[ix], &[&liquidity]).await;
+    assert_fx_error(result, FxError::InvalidFxFeed);
+    assert_eq!(env.balance(&swap.to_account).await, 0);
+}
+
+#[tokio::test]
+async fn a_stale_second_leg_makes_the_rate_stale() {
+    let mut env = sar_idr().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, crossed(), VALID_FOR).await.unwrap();
+
+    // Only the second leg is outdated
+    let now = env.now().await;
+    let cross_feed = env.cross_feed.unwrap();
+    env.publish_price(&cross_feed, 64, -6, now - 2 * MAX_RATE_AGE as i64, 1);
+    assert_fx_error(env.execute(&swap).await, FxError::StaleRate);
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use common::{assert_fx_error, TestEnv, LIQUIDITY};
+use m10_fx_solana::error::FxError;
+use rust_decimal::Decimal;
+use solana_sdk::signature::Signer;
+use std::ops::Range;
+use std::time::Duration;
+
+const VALID_FOR: Duration = Duration::from_secs(300);
+
+/// Limits any USD -> EUR rate above 0.8 has already crossed
+fn crossed() -> Range<Decimal> {
+    Decimal::new(5, 1)..Decimal::new(8, 1)
+}
+
+#[tokio::test]
+async fn payouts_are_scaled_up_to_more_decimals() {
+    let mut env = TestEnv::with_pyth_feed(912_345_678, -9).await;
+    let eur_mint = env.eur.mint;
+    env.set_decimals(&eur_mint, 6);
+    let swap = env.swap(10_001);
+    env.initiate(&swap, crossed(), VALID_FOR).await.unwrap();
+
+    let fx_data = env.fx_data(&swap.fx_account).await;
+    assert_eq!((fx_data.from_decimals, fx_data.to_decimals), (2, 6));
+
+    // 100.01 * 0.912345678 = 91.24369125678, rounded down to the millionth
+    env.execute(&swap).await.unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, 91_243_691);
+    assert_eq!(
+        env.balance(&env.eur.liquidity.pubkey()).await,
+        LIQUIDITY - 91_243_691
+    );
+}
+
+#[tokio::test]
+async fn payouts_are_scaled_down_to_fewer_decimals() {
+    let mut env = TestEnv::with_pyth_feed(912_345_678, -9).await;
+    let usd_mint = env.usd.mint;
+    env.set_decimals(&usd_mint, 6);
+    let swap = env.swap(100_010_000);
+    env.initiate(&swap, crossed(), VALID_FOR).await.unwrap();
+
+    let fx_data = env.fx_data(&swap.fx_account).await;
+    assert_eq!((fx_data.from_decimals, fx_data.to_decimals), (6, 2));
+
+    // 100.01 * 0.912345678 = 91.24369125678, rounded down to the cent
+    env.execute(&swap).await.unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, 9_124);
+    assert_eq!(
+        env.balance(&env.eur.liquidity.pubkey()).await,
+        LIQUIDITY - 9_124
+    );
+}
+
+#[tokio::test]
+async fn payouts_above_i64_max_are_rejected() {
+    let mut env = TestEnv::with_static_feed().await;
+    let eur_mint = env.eur.mint;
+    env.set_decimals(&eur_mint, 6);
+    // 20,000,000,000,000.00 USD at 0.9 is 1.8e19 millionths of a EUR, within u64 but not i64
+    let swap = env.swap(2_000_000_000_000_000);
+    env.initiate(&swap, crossed(), VALID_FOR).await.unwrap();
+
+    assert_fx_error(env.execute(&swap).await, FxError::InvalidAmount);
+    assert_eq!(env.balance(&swap.to_account).await, 0);
+    assert!(env.account(&swap.fx_account).await.is_some());
+}
@@ -0,0 +1,423 @@
+mod common;
+
+use common::{assert_fx_error, PatchAccounts, TestEnv, LIQUIDITY, MAX_RATE_AGE};
+use m10_fx_solana::error::FxError;
+use m10_fx_solana::instruction;
+use m10_fx_solana::rates::{Oracle, EUR_TO_USD};
+use m10_fx_solana::state::{FxData, FxFeed, MAX_FX_FEEDS};
+use rust_decimal::Decimal;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::system_program;
+use solana_program_test::BanksClientError;
+use solana_sdk::account::Account;
+use solana_sdk::signature::{Keypair, Signer};
+use std::ops::Range;
+use std::time::Duration;
+
+const AMOUNT: u64 = 10_000;
+const VALID_FOR: Duration = Duration::from_secs(300);
+
+fn limits() -> Range<Decimal> {
+    Decimal::new(8, 1)..Decimal::new(10, 1)
+}
+
+#[tokio::test]
+async fn inverted_limits_are_an_invalid_request() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    let limits = limits();
+    let result = env
+        .initiate(&swap, limits.end..limits.start, VALID_FOR)
+        .await;
+    assert_fx_error(result, FxError::InvalidRequest);
+}
+
+#[tokio::test]
+async fn executing_an_uninitialized_swap_is_an_invalid_request() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.set_account(
+        &swap.fx_account,
+        Account::new(
+            Rent::default().minimum_balance(FxData::LEN),
+            FxData::LEN,
+            &m10_fx_solana::id(),
+        ),
+    );
+    assert_fx_error(env.execute(&swap).await, FxError::InvalidRequest);
+}
+
+#[tokio::test]
+async fn cancel_requires_the_initializer_signature() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits(), VALID_FOR).await.unwrap();
+
+    let mut ix = env.cancel_ix(&swap);
+    ix.unsign(&swap.initializer.pubkey());
+    let result = env.process(&[ix], &[]).await;
+    assert_fx_error(result, FxError::MissingSignature);
+}
+
+#[tokio::test]
+async fn swapping_to_a_non_token_account_is_rejected() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    let swap = common::Swap {
+        to_account: swap.initializer.pubkey(),
+        ..swap
+    };
+    let result = env.initiate(&swap, limits(), VALID_FOR).await;
+    assert_fx_error(result, FxError::InvalidTokenId);
+}
+
+#[tokio::test]
+async fn initiate_requires_the_initializer_signature() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    let mut ix = env.initiate_ix(&swap, limits(), VALID_FOR);
+    ix.unsign(&swap.initializer.pubkey());
+    let result = env.process(&[ix], &[&swap.holding]).await;
+    assert_fx_error(result, FxError::MissingSignature);
+}
+
+#[tokio::test]
+async fn fx_account_must_be_derived_from_the_initializer() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    let mut ix = env.initiate_ix(&swap, limits(), VALID_FOR);
+    ix.replace_account(&swap.fx_account, Pubkey::new_unique());
+    let result = env
+        .process(&[ix], &[&swap.holding, &swap.initializer])
+        .await;
+    assert_fx_error(result, FxError::InvalidFxAccount);
+}
+
+/// Initiate the [`swap`] with the [`account`] replaced by [`spoofed`]
+async fn initiate_spoofed(
+    env: &mut TestEnv,
+    swap: &common::Swap,
+    account: &Pubkey,
+    spoofed: Pubkey,
+) -> Result<(), BanksClientError> {
+    let mut ix = env.initiate_ix(swap, limits(), VALID_FOR);
+    ix.replace_account(account, spoofed);
+    env.process(&[ix], &[&swap.holding, &swap.initializer])
+        .await
+}
+
+#[tokio::test]
+async fn initiate_requires_the_swap_vault() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    let other = env.swap(AMOUNT);
+    let result = initiate_spoofed(&mut env, &swap, &swap.vault(), other.vault()).await;
+    assert_fx_error(result, FxError::InvalidVault);
+}
+
+#[tokio::test]
+async fn initiate_requires_the_token_program() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    let result = initiate_spoofed(&mut env, &swap, &spl_token::id(), Pubkey::new_unique()).await;
+    assert_fx_error(result, FxError::InvalidTokenProgram);
+}
+
+#[tokio::test]
+async fn initiate_requires_the_system_program() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    let result =
+        initiate_spoofed(&mut env, &swap, &system_program::id(), Pubkey::new_unique()).await;
+    assert_fx_error(result, FxError::InvalidSystemProgram);
+}
+
+#[tokio::test]
+async fn initiate_requires_the_mint_of_the_receiving_account() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    let (from_mint, to_mint) = (env.usd.mint, env.eur.mint);
+    let result = initiate_spoofed(&mut env, &swap, &to_mint, from_mint).await;
+    assert_fx_error(result, FxError::MintMismatch);
+}
+
+/// A copy of the initiated [`swap`]'s FX account, owned by another program
+async fn spoofed_fx_account(env: &mut TestEnv, swap: &common::Swap) -> Pubkey {
+    let mut account = env.account(&swap.fx_account).await.unwrap();
+    account.owner = Pubkey::new_unique();
+    let spoofed = Pubkey::new_unique();
+    env.set_account(&spoofed, account);
+    spoofed
+}
+
+#[tokio::test]
+async fn executing_a_spoofed_fx_account_is_rejected() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, Decimal::new(5, 1)..Decimal::new(8, 1), VALID_FOR)
+        .await
+        .unwrap();
+
+    let spoofed = spoofed_fx_account(&mut env, &swap).await;
+    let liquidity = Keypair::from_bytes(&env.eur.liquidity.to_bytes()).unwrap();
+    let mut ix = env.execute_ix(&swap, &liquidity.pubkey(), None);
+    ix.replace_account(&swap.fx_account, spoofed);
+    let result = env.process(&[ix], &[&liquidity]).await;
+    assert_fx_error(result, FxError::InvalidFxAccount);
+}
+
+#[tokio::test]
+async fn execute_requires_the_swap_vault_and_token_program() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, Decimal::new(5, 1)..Decimal::new(8, 1), VALID_FOR)
+        .await
+        .unwrap();
+    let other = env.swap(AMOUNT);
+    env.initiate(&other, limits(), VALID_FOR).await.unwrap();
+
+    let liquidity = Keypair::from_bytes(&env.eur.liquidity.to_bytes()).unwrap();
+    let mut ix = env.execute_ix(&swap, &liquidity.pubkey(), None);
+    ix.replace_account(&swap.vault(), other.vault());
+    let result = env.process(&[ix], &[&liquidity]).await;
+    assert_fx_error(result, FxError::InvalidVault);
+
+    let mut ix = env.execute_ix(&swap, &liquidity.pubkey(), None);
+    ix.replace_account(&spl_token::id(), Pubkey::new_unique());
+    let result = env.process(&[ix], &[&liquidity]).await;
+    assert_fx_error(result, FxError::InvalidTokenProgram);
+}
+
+#[tokio::test]
+async fn execute_requires_the_swap_receipt_and_system_program() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, Decimal::new(5, 1)..Decimal::new(8, 1), VALID_FOR)
+        .await
+        .unwrap();
+    let other = env.swap(AMOUNT);
+
+    let liquidity = Keypair::from_bytes(&env.eur.liquidity.to_bytes()).unwrap();
+    let mut ix = env.execute_ix(&swap, &liquidity.pubkey(), None);
+    ix.replace_account(&swap.receipt(), other.receipt());
+    let result = env.process(&[ix], &[&liquidity]).await;
+    assert_fx_error(result, FxError::InvalidReceipt);
+
+    let mut ix = env.execute_ix(&swap, &liquidity.pubkey(), None);
+    ix.replace_account(&system_program::id(), Pubkey::new_unique());
+    let result = env.process(&[ix], &[&liquidity]).await;
+    assert_fx_error(result, FxError::InvalidSystemProgram);
+}
+
+#[tokio::test]
+async fn cancelling_a_spoofed_fx_account_is_rejected() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits(), VALID_FOR).await.unwrap();
+
+    let spoofed = spoofed_fx_account(&mut env, &swap).await;
+    let mut ix = env.cancel_ix(&swap);
+    ix.replace_account(&swap.fx_account, spoofed);
+    let result = env.process(&[ix], &[&swap.initializer]).await;
+    assert_fx_error(result, FxError::InvalidFxAccount);
+}
+
+#[tokio::test]
+async fn amount_must_match_the_holding_balance() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    let swap = common::Swap {
+        amount: AMOUNT + 1,
+        ..swap
+    };
+    let result = env.initiate(&swap, limits(), VALID_FOR).await;
+    assert_fx_error(result, FxError::InvalidAmount);
+}
+
+#[tokio::test]
+async fn unregistered_feed_is_rejected() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.feed = Some(FxFeed {
+        feed: EUR_TO_USD,
+        ..env.feed()
+    });
+    let result = env.initiate(&swap, limits(), VALID_FOR).await;
+    assert_fx_error(result, FxError::InvalidFxFeed);
+}
+
+#[tokio::test]
+async fn swapping_without_liquidity_is_rejected() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    let mint = env.mint();
+    let to_account = env.token_account(&mint, &swap.initializer.pubkey(), 0);
+    let swap = common::Swap { to_account, ..swap };
+    let mut ix = env.initiate_ix(&swap, limits(), VALID_FOR);
+    ix.replace_account(&env.eur.mint, mint);
+    let result = env
+        .process(&[ix], &[&swap.holding, &swap.initializer])
+        .await;
+    assert_fx_error(result, FxError::NoLiquidity);
+}
+
+#[tokio::test]
+async fn only_the_admin_can_add_feeds() {
+    let mut env = TestEnv::with_static_feed().await;
+    let intruder = Keypair::new();
+    let feed = FxFeed {
+        from_mint: env.eur.mint,
+        to_mint: env.usd.mint,
+        ..env.feed()
+    };
+    let ix = instruction::add_fx_feed(intruder.pubkey(), feed);
+    let result = env.process(&[ix], &[&intruder]).await;
+    assert_fx_error(result, FxError::Unauthorized);
+}
+
+#[tokio::test]
+async fn feed_registry_is_bounded() {
+    let mut env = TestEnv::with_static_feed().await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let feed = |env: &TestEnv| FxFeed {
+        from_mint: Pubkey::new_unique(),
+        to_mint: Pubkey::new_unique(),
+        ..env.feed()
+    };
+    for _ in 1..MAX_FX_FEEDS {
+        let ix = instruction::add_fx_feed(admin.pubkey(), feed(&env));
+        env.process(&[ix], &[&admin]).await.unwrap();
+    }
+    let ix = instruction::add_fx_feed(admin.pubkey(), feed(&env));
+    let result = env.process(&[ix], &[&admin]).await;
+    assert_fx_error(result, FxError::RegistryFull);
+}
+
+#[tokio::test]
+async fn stale_rates_are_rejected() {
+    let mut env = TestEnv::with_pyth_feed(9_000, -4).await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits(), VALID_FOR).await.unwrap();
+
+    let now = env.now().await;
+    env.set_pyth_price(9_000, -4, now - 2 * MAX_RATE_AGE as i64);
+    assert_fx_error(env.execute(&swap).await, FxError::StaleRate);
+}
+
+#[tokio::test]
+async fn oracles_other_than_the_registered_one_are_rejected() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.oracle = Oracle::Pyth;
+    let result = env.initiate(&swap, limits(), VALID_FOR).await;
+    assert_fx_error(result, FxError::InvalidFxFeed);
+}
+
+#[tokio::test]
+async fn unbounded_rate_ages_never_go_stale() {
+    let mut env = TestEnv::with_pyth_feed(9_000, -4).await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let feed = FxFeed {
+        max_age: u64::MAX,
+        ..env.feed()
+    };
+    let ix = instruction::update_fx_feed(admin.pubkey(), feed);
+    env.process(&[ix], &[&admin]).await.unwrap();
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, Decimal::new(5, 1)..Decimal::new(8, 1), VALID_FOR)
+        .await
+        .unwrap();
+
+    env.set_pyth_price(9_000, -4, 0);
+    env.execute(&swap).await.unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, 9_000);
+}
+
+#[tokio::test]
+async fn non_positive_rates_are_rejected() {
+    let mut env = TestEnv::with_pyth_feed(9_000, -4).await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits(), VALID_FOR).await.unwrap();
+
+    let now = env.now().await;
+    env.set_pyth_price(0, -4, now);
+    assert_fx_error(env.execute(&swap).await, FxError::InvalidRate);
+}
+
+#[tokio::test]
+async fn unknown_account_versions_are_rejected() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits(), VALID_FOR).await.unwrap();
+
+    let mut account = env.account(&swap.fx_account).await.unwrap();
+    account.data[0] = u8::MAX;
+    env.set_account(&swap.fx_account, account);
+    assert_fx_error(env.execute(&swap).await, FxError::UnknownVersion);
+    assert_eq!(
+        FxData::unpack(&env.account(&swap.fx_account).await.unwrap().data).unwrap_err(),
+        FxError::UnknownVersion.into()
+    );
+}
+
+#[tokio::test]
+async fn empty_fills_are_an_invalid_amount() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, Decimal::new(5, 1)..Decimal::new(8, 1), VALID_FOR)
+        .await
+        .unwrap();
+
+    let liquidity = Keypair::from_bytes(&env.eur.liquidity.to_bytes()).unwrap();
+    assert_fx_error(env.fill(&swap, &liquidity, 0).await, FxError::InvalidAmount);
+}
+
+#[tokio::test]
+async fn fills_converting_to_nothing_are_an_invalid_amount() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, Decimal::new(5, 1)..Decimal::new(8, 1), VALID_FOR)
+        .await
+        .unwrap();
+
+    // 1 cent at 0.9 rounds down to nothing
+    let liquidity = Keypair::from_bytes(&env.eur.liquidity.to_bytes()).unwrap();
+    assert_fx_error(env.fill(&swap, &liquidity, 1).await, FxError::InvalidAmount);
+    assert_eq!(env.fx_data(&swap.fx_account).await.amount_filled, 0);
+}
+
+#[tokio::test]
+async fn only_the_selected_provider_can_fill() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, Decimal::new(5, 1)..Decimal::new(8, 1), VALID_FOR)
+        .await
+        .unwrap();
+
+    let other = env.add_liquidity(&env.eur.mint.clone()).await;
+    assert_fx_error(
+        env.fill(&swap, &other, AMOUNT).await,
+        FxError::InvalidRequest,
+    );
+}
+
+#[tokio::test]
+async fn unregistered_providers_cannot_fill() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, Decimal::new(5, 1)..Decimal::new(8, 1), VALID_FOR)
+        .await
+        .unwrap();
+
+    let liquidity = Keypair::new();
+    env.set_account(
+        &liquidity.pubkey(),
+        common::token_account(&env.eur.mint, &liquidity.pubkey(), LIQUIDITY),
+    );
+    assert_fx_error(
+        env.fill(&swap, &liquidity, AMOUNT).await,
+        FxError::NoLiquidity,
+    );
+}
@@ -0,0 +1,201 @@
+mod common;
+
+use common::{Swap, TestEnv, STATIC_RATE};
+use m10_fx_solana::events::{
+    Event, FxLog, SwapCancelled, SwapConditionsNotMet, SwapExecuted, SwapInitiated, SwapRefunded,
+    PROGRAM_DATA_PREFIX,
+};
+use m10_fx_solana::instruction;
+use m10_fx_solana::state::{ExpiryPolicy, SwapKind, Trigger};
+use rust_decimal::Decimal;
+use solana_sdk::signature::{Keypair, Signer};
+use std::ops::Range;
+use std::time::Duration;
+
+const AMOUNT: u64 = 10_000;
+const VALID_FOR: Duration = Duration::from_secs(300);
+
+/// Limits the static rate of 0.9 has already crossed
+fn crossed() -> Range<Decimal> {
+    Decimal::new(5, 1)..Decimal::new(8, 1)
+}
+
+/// Limits around the static rate of 0.9
+fn pending() -> Range<Decimal> {
+    Decimal::new(8, 1)..Decimal::new(10, 1)
+}
+
+async fn initiate(env: &mut TestEnv, swap: &Swap, limits: Range<Decimal>) -> Vec<FxLog> {
+    let ix = env.initiate_ix(swap, limits, VALID_FOR);
+    env.events(&[ix], &[&swap.holding, &swap.initializer]).await
+}
+
+async fn execute(env: &mut TestEnv, swap: &Swap) -> Vec<FxLog> {
+    let liquidity = Keypair::from_bytes(&env.eur.liquidity.to_bytes()).unwrap();
+    let ix = env.execute_ix(swap, &liquidity.pubkey(), None);
+    env.events(&[ix], &[&liquidity]).await
+}
+
+#[tokio::test]
+async fn initiate_logs_the_swap() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    let events = initiate(&mut env, &swap, pending()).await;
+
+    let fx_data = env.fx_data(&swap.fx_account).await;
+    assert_eq!(
+        events,
+        vec![FxLog::SwapInitiated(SwapInitiated {
+            fx_account: swap.fx_account,
+            initializer: swap.initializer.pubkey(),
+            from_holding: swap.holding.pubkey(),
+            to_holding: swap.to_account,
+            amount: AMOUNT,
+            lower_limit: pending().start,
+            upper_limit: pending().end,
+            valid_until: fx_data.valid_until,
+            kind: SwapKind::Oco,
+            expiry: ExpiryPolicy::SettleAtMarket,
+        })]
+    );
+}
+
+#[tokio::test]
+async fn execute_logs_the_settlement() {
+    let mut env = TestEnv::with_static_feed().await;
+    env.set_fees(100, 50).await.unwrap();
+    let swap = env.swap(AMOUNT);
+    initiate(&mut env, &swap, crossed()).await;
+    let events = execute(&mut env, &swap).await;
+
+    // 9_000 converted, of which 1% fee & 0.5% spread
+    assert_eq!(
+        events,
+        vec![FxLog::SwapExecuted(SwapExecuted {
+            fx_account: swap.fx_account,
+            to_holding: swap.to_account,
+            rate: STATIC_RATE,
+            round_id: 0,
+            cross_round_id: None,
+            amount_in: AMOUNT,
+            amount_out: 8_865,
+            fee: 90,
+            spread: 45,
+            amount_filled: AMOUNT,
+            reason: Trigger::UpperLimit,
+        })]
+    );
+}
+
+#[tokio::test]
+async fn expired_execution_logs_the_expiry() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    initiate(&mut env, &swap, pending()).await;
+    env.warp(VALID_FOR).await;
+
+    match execute(&mut env, &swap).await.as_slice() {
+        [FxLog::SwapExecuted(executed)] => assert_eq!(executed.reason, Trigger::Expiry),
+        events => panic!("Unexpected events {:?}", events),
+    }
+}
+
+#[tokio::test]
+async fn cross_rate_execution_logs_the_round_of_either_feed() {
+    let mut env = TestEnv::with_cross_feeds(false, false).await;
+    let now = env.now().await;
+    let cross_feed = env.cross_feed.unwrap();
+    env.publish_price(&env.feed(), 2, 0, now, 7);
+    env.publish_price(&cross_feed, 3, 0, now, 9);
+    let swap = env.swap(AMOUNT);
+    initiate(&mut env, &swap, crossed()).await;
+
+    match execute(&mut env, &swap).await.as_slice() {
+        [FxLog::SwapExecuted(executed)] => {
+            assert_eq!(executed.rate, Decimal::from(6));
+            assert_eq!(executed.round_id, 7);
+            assert_eq!(executed.cross_round_id, Some(9));
+        }
+        events => panic!("Unexpected events {:?}", events),
+    }
+}
+
+#[tokio::test]
+async fn failed_execution_logs_the_unmet_conditions() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    initiate(&mut env, &swap, pending()).await;
+    let fx_data = env.fx_data(&swap.fx_account).await;
+    let now = env.now().await;
+
+    assert_eq!(
+        execute(&mut env, &swap).await,
+        vec![FxLog::SwapConditionsNotMet(SwapConditionsNotMet {
+            fx_account: swap.fx_account,
+            rate: STATIC_RATE,
+            lower_limit: pending().start,
+            upper_limit: pending().end,
+            valid_until: fx_data.valid_until,
+            now,
+        })]
+    );
+}
+
+#[tokio::test]
+async fn cancel_logs_the_refunded_amount() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    initiate(&mut env, &swap, pending()).await;
+    let ix = env.cancel_ix(&swap);
+
+    assert_eq!(
+        env.events(&[ix], &[&swap.initializer]).await,
+        vec![FxLog::SwapCancelled(SwapCancelled {
+            fx_account: swap.fx_account,
+            initializer: swap.initializer.pubkey(),
+            refund_account: swap.refund_account,
+            amount_refunded: AMOUNT,
+        })]
+    );
+}
+
+#[tokio::test]
+async fn refund_logs_the_expired_swap() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = Swap {
+        expiry: ExpiryPolicy::Refund,
+        ..env.swap(AMOUNT)
+    };
+    initiate(&mut env, &swap, pending()).await;
+    let fx_data = env.fx_data(&swap.fx_account).await;
+    env.warp(VALID_FOR).await;
+    let ix = instruction::refund(
+        swap.initializer.pubkey(),
+        swap.refund_account,
+        swap.fx_account,
+    );
+
+    assert_eq!(
+        env.events(&[ix], &[]).await,
+        vec![FxLog::SwapRefunded(SwapRefunded {
+            fx_account: swap.fx_account,
+            initializer: swap.initializer.pubkey(),
+            refund_account: swap.refund_account,
+            amount_refunded: AMOUNT,
+            valid_until: fx_data.valid_until,
+        })]
+    );
+}
+
+#[test]
+fn unknown_log_messages_are_skipped() {
+    let executed = base64::encode(SwapExecuted::DISCRIMINATOR);
+    let messages = [
+        "Program log: Trying to execute max_amount=None".to_string(),
+        format!("{}{}", PROGRAM_DATA_PREFIX, base64::encode(b"unknown!")),
+        // Known discriminator, but no event data
+        format!("{}{}", PROGRAM_DATA_PREFIX, executed),
+        format!("{}{} AAAA", PROGRAM_DATA_PREFIX, executed),
+    ];
+    assert!(FxLog::from_log_messages(&messages).is_empty());
+}
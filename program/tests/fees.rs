@@ -0,0 +1,100 @@
+mod common;
+
+use common::{assert_fx_error, TestEnv, LIQUIDITY};
+use m10_fx_solana::error::FxError;
+use m10_fx_solana::instruction;
+use m10_fx_solana::state::BPS;
+use m10_fx_solana::utils::pda_config;
+use rust_decimal::Decimal;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use std::ops::Range;
+use std::time::Duration;
+
+const AMOUNT: u64 = 10_000;
+const VALID_FOR: Duration = Duration::from_secs(300);
+
+/// Limits the static rate of 0.9 has already crossed
+fn limits() -> Range<Decimal> {
+    Decimal::new(5, 1)..Decimal::new(8, 1)
+}
+
+#[tokio::test]
+async fn fee_and_spread_are_deducted_from_the_converted_amount() {
+    let mut env = TestEnv::with_static_feed().await;
+    env.set_fees(100, 50).await.unwrap();
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits(), VALID_FOR).await.unwrap();
+    env.execute(&swap).await.unwrap();
+
+    // 9_000 converted, of which 1% fee & 0.5% spread
+    assert_eq!(env.balance(&swap.to_account).await, 8_865);
+    assert_eq!(env.balance(&env.fee_account.unwrap()).await, 90);
+    assert_eq!(
+        env.balance(&env.eur.liquidity.pubkey()).await,
+        LIQUIDITY - 8_955
+    );
+}
+
+#[tokio::test]
+async fn charges_are_rounded_down() {
+    let mut env = TestEnv::with_static_feed().await;
+    env.set_fees(30, 15).await.unwrap();
+    let swap = env.swap(1_000);
+    env.initiate(&swap, limits(), VALID_FOR).await.unwrap();
+    env.execute(&swap).await.unwrap();
+
+    // 900 converted, of which a 2.7 fee & 1.35 spread
+    assert_eq!(env.balance(&env.fee_account.unwrap()).await, 2);
+    assert_eq!(env.balance(&swap.to_account).await, 897);
+}
+
+#[tokio::test]
+async fn fees_can_be_updated() {
+    let mut env = TestEnv::with_static_feed().await;
+    env.set_fees(100, 50).await.unwrap();
+    env.set_fees(0, 100).await.unwrap();
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits(), VALID_FOR).await.unwrap();
+    env.execute(&swap).await.unwrap();
+
+    assert_eq!(env.balance(&swap.to_account).await, 8_910);
+    assert_eq!(env.balance(&env.fee_account.unwrap()).await, 0);
+}
+
+#[tokio::test]
+async fn only_the_admin_can_set_fees() {
+    let mut env = TestEnv::with_static_feed().await;
+    env.set_fees(100, 50).await.unwrap();
+    let intruder = Keypair::new();
+    let ix = instruction::set_fees(intruder.pubkey(), 0, 0, intruder.pubkey());
+    let result = env.process(&[ix], &[&intruder]).await;
+    assert_fx_error(result, FxError::Unauthorized);
+}
+
+#[tokio::test]
+async fn only_the_upgrade_authority_creates_the_fee_config() {
+    let mut env = TestEnv::with_static_feed().await;
+    let intruder = Keypair::new();
+    let ix = instruction::set_fees(intruder.pubkey(), 0, BPS, intruder.pubkey());
+    let result = env.process(&[ix], &[&intruder]).await;
+    assert_fx_error(result, FxError::Unauthorized);
+    assert!(env.account(&pda_config().0).await.is_none());
+}
+
+#[tokio::test]
+async fn charges_cannot_exceed_the_converted_amount() {
+    let mut env = TestEnv::with_static_feed().await;
+    assert_fx_error(env.set_fees(9_000, 1_001).await, FxError::InvalidRequest);
+}
+
+#[tokio::test]
+async fn fees_are_collected_by_the_fee_collector() {
+    let mut env = TestEnv::with_static_feed().await;
+    env.set_fees(100, 50).await.unwrap();
+    let eur_mint = env.eur.mint;
+    env.fee_account = Some(env.token_account(&eur_mint, &Pubkey::new_unique(), 0));
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits(), VALID_FOR).await.unwrap();
+    assert_fx_error(env.execute(&swap).await, FxError::InvalidRequest);
+}
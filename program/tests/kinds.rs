@@ -0,0 +1,203 @@
+mod common;
+
+use common::{assert_fx_error, Swap, TestEnv, LIQUIDITY};
+use m10_fx_solana::error::FxError;
+use m10_fx_solana::instruction;
+use m10_fx_solana::rates::FxRate;
+use m10_fx_solana::state::{ExpiryPolicy, FxData, SwapKind, Trigger};
+use rust_decimal::Decimal;
+use solana_program::program_pack::Pack;
+use solana_program::rent::Rent;
+use solana_program::system_instruction;
+use solana_sdk::signature::Signer;
+use std::ops::Range;
+use std::time::Duration;
+
+const AMOUNT: u64 = 10_000;
+const VALID_FOR: Duration = Duration::from_secs(300);
+/// Pyth prices are quoted with 4 decimals
+const EXPONENT: i32 = -4;
+
+fn limits() -> Range<Decimal> {
+    Decimal::new(8, 1)..Decimal::new(10, 1)
+}
+
+async fn initiate(env: &mut TestEnv, kind: SwapKind, expiry: ExpiryPolicy) -> Swap {
+    let swap = Swap {
+        kind,
+        expiry,
+        ..env.swap(AMOUNT)
+    };
+    env.initiate(&swap, limits(), VALID_FOR).await.unwrap();
+    assert_eq!(env.fx_data(&swap.fx_account).await.kind, kind);
+    swap
+}
+
+#[tokio::test]
+async fn limit_order_executes_at_or_above_the_upper_limit() {
+    let mut env = TestEnv::with_pyth_feed(9_000, EXPONENT).await;
+    let swap = initiate(&mut env, SwapKind::Limit, ExpiryPolicy::SettleAtMarket).await;
+
+    assert_fx_error(env.execute(&swap).await, FxError::SwapConditionsNotMet);
+    // A falling rate doesn't stop the loss
+    env.set_rate(7_000, EXPONENT).await;
+    assert_fx_error(env.execute(&swap).await, FxError::SwapConditionsNotMet);
+
+    env.set_rate(10_000, EXPONENT).await;
+    env.execute(&swap).await.unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, 10_000);
+}
+
+#[tokio::test]
+async fn stop_order_executes_at_or_below_the_lower_limit() {
+    let mut env = TestEnv::with_pyth_feed(9_000, EXPONENT).await;
+    let swap = initiate(&mut env, SwapKind::Stop, ExpiryPolicy::SettleAtMarket).await;
+
+    assert_fx_error(env.execute(&swap).await, FxError::SwapConditionsNotMet);
+    // A rising rate doesn't take the profit
+    env.set_rate(11_000, EXPONENT).await;
+    assert_fx_error(env.execute(&swap).await, FxError::SwapConditionsNotMet);
+
+    env.set_rate(8_000, EXPONENT).await;
+    env.execute(&swap).await.unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, 8_000);
+}
+
+#[tokio::test]
+async fn oco_order_executes_at_either_limit() {
+    let mut env = TestEnv::with_pyth_feed(9_000, EXPONENT).await;
+    let take_profit = initiate(&mut env, SwapKind::Oco, ExpiryPolicy::SettleAtMarket).await;
+    let stop_loss = initiate(&mut env, SwapKind::Oco, ExpiryPolicy::SettleAtMarket).await;
+
+    assert_fx_error(
+        env.execute(&take_profit).await,
+        FxError::SwapConditionsNotMet,
+    );
+
+    env.set_rate(10_500, EXPONENT).await;
+    env.execute(&take_profit).await.unwrap();
+    assert_eq!(env.balance(&take_profit.to_account).await, 10_500);
+
+    env.set_rate(7_500, EXPONENT).await;
+    env.execute(&stop_loss).await.unwrap();
+    assert_eq!(env.balance(&stop_loss.to_account).await, 7_500);
+}
+
+#[tokio::test]
+async fn expired_swap_settles_at_market() {
+    let mut env = TestEnv::with_pyth_feed(9_000, EXPONENT).await;
+    let swap = initiate(&mut env, SwapKind::Limit, ExpiryPolicy::SettleAtMarket).await;
+
+    env.warp(VALID_FOR).await;
+    env.set_rate(9_000, EXPONENT).await;
+    env.execute(&swap).await.unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, 9_000);
+}
+
+#[tokio::test]
+async fn expired_swap_is_refunded() {
+    let mut env = TestEnv::with_pyth_feed(9_000, EXPONENT).await;
+    let swap = initiate(&mut env, SwapKind::Limit, ExpiryPolicy::Refund).await;
+
+    assert_fx_error(env.refund(&swap).await, FxError::SwapConditionsNotMet);
+
+    env.warp(VALID_FOR).await;
+    env.set_rate(9_000, EXPONENT).await;
+    assert_fx_error(env.execute(&swap).await, FxError::SwapExpired);
+
+    env.refund(&swap).await.unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, 0);
+    assert_eq!(env.balance(&swap.refund_account).await, AMOUNT);
+    assert_eq!(env.balance(&env.usd.liquidity.pubkey()).await, LIQUIDITY);
+    assert!(env.account(&swap.vault()).await.is_none());
+    assert!(env.account(&swap.fx_account).await.is_none());
+}
+
+#[tokio::test]
+async fn refunded_fx_accounts_cannot_be_revived() {
+    let mut env = TestEnv::with_pyth_feed(9_000, EXPONENT).await;
+    let swap = initiate(&mut env, SwapKind::Limit, ExpiryPolicy::Refund).await;
+    env.warp(VALID_FOR).await;
+
+    // Refund the rent of the closed FX account within the same transaction
+    let refund_ix = instruction::refund(
+        swap.initializer.pubkey(),
+        swap.refund_account,
+        swap.fx_account,
+    );
+    let revive_ix = system_instruction::transfer(
+        &env.context.payer.pubkey(),
+        &swap.fx_account,
+        Rent::default().minimum_balance(FxData::LEN),
+    );
+    let result = env
+        .process(&[refund_ix.clone(), revive_ix, refund_ix], &[])
+        .await;
+    assert_fx_error(result, FxError::InvalidFxAccount);
+
+    env.refund(&swap).await.unwrap();
+    assert_eq!(env.balance(&swap.refund_account).await, AMOUNT);
+    assert!(env.account(&swap.fx_account).await.is_none());
+}
+
+#[tokio::test]
+async fn expired_swap_settling_at_market_is_not_refunded() {
+    let mut env = TestEnv::with_pyth_feed(9_000, EXPONENT).await;
+    let swap = initiate(&mut env, SwapKind::Limit, ExpiryPolicy::SettleAtMarket).await;
+
+    env.warp(VALID_FOR).await;
+    assert_fx_error(env.refund(&swap).await, FxError::InvalidRequest);
+}
+
+#[tokio::test]
+async fn triggered_swap_executes_before_expiry_regardless_of_policy() {
+    let mut env = TestEnv::with_pyth_feed(9_000, EXPONENT).await;
+    let swap = initiate(&mut env, SwapKind::Stop, ExpiryPolicy::Refund).await;
+
+    env.set_rate(7_000, EXPONENT).await;
+    env.execute(&swap).await.unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, 7_000);
+}
+
+#[tokio::test]
+async fn local_evaluation_agrees_with_execution() {
+    let mut env = TestEnv::with_pyth_feed(9_000, EXPONENT).await;
+    let swap = initiate(&mut env, SwapKind::Limit, ExpiryPolicy::Refund).await;
+    let fx_data = env.fx_data(&swap.fx_account).await;
+    let now = env.now().await;
+    let quote = |price| FxRate {
+        rate: Decimal::new(price, -EXPONENT as u32),
+        round_id: 0,
+        cross_round_id: None,
+        timestamp: Some(now),
+    };
+
+    assert!(matches!(
+        fx_data.check_execution(&quote(9_000), now),
+        Err(FxError::SwapConditionsNotMet)
+    ));
+    let stale = FxRate {
+        timestamp: Some(now - 2 * fx_data.max_rate_age as i64),
+        ..quote(10_000)
+    };
+    assert!(matches!(
+        fx_data.check_execution(&stale, now),
+        Err(FxError::StaleRate)
+    ));
+    let expired = FxRate {
+        timestamp: Some(fx_data.valid_until),
+        ..quote(10_000)
+    };
+    assert!(matches!(
+        fx_data.check_execution(&expired, fx_data.valid_until),
+        Err(FxError::SwapExpired)
+    ));
+    assert!(matches!(
+        fx_data.check_execution(&quote(10_000), now),
+        Ok(Trigger::UpperLimit)
+    ));
+
+    assert_fx_error(env.execute(&swap).await, FxError::SwapConditionsNotMet);
+    env.set_rate(10_000, EXPONENT).await;
+    env.execute(&swap).await.unwrap();
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use common::{assert_fx_error, Swap, TestEnv, STATIC_RATE};
+use m10_fx_solana::error::FxError;
+use m10_fx_solana::state::{SettlementReceipt, Trigger};
+use rust_decimal::Decimal;
+use solana_program::program_pack::Pack;
+use solana_program::rent::Rent;
+use solana_sdk::signature::{Keypair, Signer};
+use std::ops::Range;
+use std::time::Duration;
+
+const AMOUNT: u64 = 10_000;
+const VALID_FOR: Duration = Duration::from_secs(300);
+
+/// Limits the static rate of 0.9 has already crossed
+fn crossed() -> Range<Decimal> {
+    Decimal::new(5, 1)..Decimal::new(8, 1)
+}
+
+/// Limits around the static rate of 0.9
+fn pending() -> Range<Decimal> {
+    Decimal::new(8, 1)..Decimal::new(10, 1)
+}
+
+async fn initiate(env: &mut TestEnv, limits: Range<Decimal>) -> Swap {
+    let swap = Swap {
+        receipt: true,
+        ..env.swap(AMOUNT)
+    };
+    env.initiate(&swap, limits, VALID_FOR).await.unwrap();
+    swap
+}
+
+async fn receipt(env: &mut TestEnv, swap: &Swap) -> Option<SettlementReceipt> {
+    let account = env.account(&swap.receipt()).await?;
+    assert_eq!(account.owner, m10_fx_solana::id());
+    Some(SettlementReceipt::unpack(&account.data).unwrap())
+}
+
+#[tokio::test]
+async fn execution_is_recorded_in_the_receipt() {
+    let mut env = TestEnv::with_static_feed().await;
+    env.set_fees(100, 50).await.unwrap();
+    let swap = initiate(&mut env, crossed()).await;
+    env.execute(&swap).await.unwrap();
+    let now = env.now().await;
+
+    // The receipt outlives the closed FX account
+    assert!(env.account(&swap.fx_account).await.is_none());
+    assert_eq!(
+        receipt(&mut env, &swap).await,
+        Some(SettlementReceipt {
+            is_initialized: true,
+            initializer: swap.initializer.pubkey(),
+            from_holding: swap.holding.pubkey(),
+            to_holding: swap.to_account,
+            amount_in: AMOUNT,
+            amount_out: 8_865,
+            rate: STATIC_RATE,
+            round_id: 0,
+            cross_round_id: None,
+            reason: Trigger::UpperLimit,
+            timestamp: now,
+        })
+    );
+}
+
+#[tokio::test]
+async fn partial_fills_add_up_in_the_receipt() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = initiate(&mut env, crossed()).await;
+    let liquidity = Keypair::from_bytes(&env.eur.liquidity.to_bytes()).unwrap();
+    env.fill(&swap, &liquidity, 4_000).await.unwrap();
+
+    let first = receipt(&mut env, &swap).await.unwrap();
+    assert_eq!((first.amount_in, first.amount_out), (4_000, 3_600));
+
+    env.fill(&swap, &liquidity, AMOUNT).await.unwrap();
+    let last = receipt(&mut env, &swap).await.unwrap();
+    assert_eq!((last.amount_in, last.amount_out), (AMOUNT, 9_000));
+}
+
+#[tokio::test]
+async fn expired_settlement_is_recorded_as_such() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = initiate(&mut env, pending()).await;
+    env.warp(VALID_FOR).await;
+    env.execute(&swap).await.unwrap();
+
+    let receipt = receipt(&mut env, &swap).await.unwrap();
+    assert_eq!(receipt.reason, Trigger::Expiry);
+}
+
+#[tokio::test]
+async fn swaps_without_receipt_record_nothing() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, crossed(), VALID_FOR).await.unwrap();
+    env.execute(&swap).await.unwrap();
+
+    assert!(receipt(&mut env, &swap).await.is_none());
+}
+
+#[tokio::test]
+async fn cancelled_swaps_return_the_receipt_rent() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = initiate(&mut env, pending()).await;
+    let fx_account = env.account(&swap.fx_account).await.unwrap();
+    let receipt_rent = Rent::default().minimum_balance(SettlementReceipt::LEN);
+    assert_eq!(
+        fx_account.lamports,
+        Rent::default().minimum_balance(fx_account.data.len()) + receipt_rent
+    );
+
+    env.cancel(&swap).await.unwrap();
+    assert!(receipt(&mut env, &swap).await.is_none());
+}
+
+#[tokio::test]
+async fn reused_nonces_cannot_add_to_an_earlier_receipt() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = initiate(&mut env, crossed()).await;
+    env.execute(&swap).await.unwrap();
+    let recorded = receipt(&mut env, &swap).await;
+
+    let initializer = Keypair::from_bytes(&swap.initializer.to_bytes()).unwrap();
+    let reused = Swap {
+        receipt: true,
+        ..env.swap_with_nonce(initializer, swap.nonce, AMOUNT)
+    };
+    let result = env.initiate(&reused, crossed(), VALID_FOR).await;
+    assert_fx_error(result, FxError::InvalidReceipt);
+    assert_eq!(receipt(&mut env, &swap).await, recorded);
+
+    // Without a receipt, the FX account can still be reused
+    let initializer = Keypair::from_bytes(&swap.initializer.to_bytes()).unwrap();
+    let reused = env.swap_with_nonce(initializer, swap.nonce, AMOUNT);
+    env.initiate(&reused, crossed(), VALID_FOR).await.unwrap();
+    env.execute(&reused).await.unwrap();
+    assert_eq!(receipt(&mut env, &swap).await, recorded);
+}
@@ -0,0 +1,194 @@
+mod common;
+
+use common::{assert_fx_error, program_data, PatchAccounts, TestEnv};
+use m10_fx_solana::error::FxError;
+use m10_fx_solana::instruction;
+use m10_fx_solana::rates::Oracle;
+use m10_fx_solana::state::{FxFeed, FxFeedRegistry, LiquidityEntry, LiquidityRegistry};
+use m10_fx_solana::utils::{pda_feed_registry, pda_liquidity_registry, pda_program_data};
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use solana_sdk::account::Account;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// A funded key without any authority over the program
+fn intruder(env: &mut TestEnv) -> Keypair {
+    let intruder = Keypair::new();
+    env.set_account(
+        &intruder.pubkey(),
+        Account::new(100_000_000_000, 0, &system_program::id()),
+    );
+    intruder
+}
+
+async fn liquidity_registry(env: &mut TestEnv) -> Option<LiquidityRegistry> {
+    let account = env.account(&pda_liquidity_registry().0).await?;
+    Some(LiquidityRegistry::unpack(&account.data).expect("Invalid liquidity registry"))
+}
+
+async fn feed_registry(env: &mut TestEnv) -> Option<FxFeedRegistry> {
+    let account = env.account(&pda_feed_registry().0).await?;
+    Some(FxFeedRegistry::unpack(&account.data).expect("Invalid feed registry"))
+}
+
+fn usd_to_eur(env: &TestEnv) -> FxFeed {
+    FxFeed {
+        from_mint: env.usd.mint,
+        to_mint: env.eur.mint,
+        feed: Pubkey::new_unique(),
+        oracle_program: Pubkey::new_unique(),
+        oracle: Oracle::Demo,
+        inverted: false,
+        max_age: 3600,
+    }
+}
+
+#[tokio::test]
+async fn upgrade_authority_creates_the_liquidity_registry() {
+    let mut env = TestEnv::empty().await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let liquidity = env.usd.liquidity.pubkey();
+    let ix = instruction::register_liquidity(admin.pubkey(), liquidity);
+    env.process(&[ix], &[&admin]).await.unwrap();
+
+    let registry = liquidity_registry(&mut env).await.unwrap();
+    assert_eq!(registry.admin, admin.pubkey());
+    assert_eq!(
+        registry.providers,
+        vec![LiquidityEntry {
+            mint: env.usd.mint,
+            liquidity,
+        }]
+    );
+}
+
+#[tokio::test]
+async fn pre_funded_liquidity_registry_is_created() {
+    let mut env = TestEnv::empty().await;
+    env.set_account(
+        &pda_liquidity_registry().0,
+        Account::new(1, 0, &system_program::id()),
+    );
+
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let liquidity = env.usd.liquidity.pubkey();
+    let ix = instruction::register_liquidity(admin.pubkey(), liquidity);
+    env.process(&[ix], &[&admin]).await.unwrap();
+
+    let registry = liquidity_registry(&mut env).await.unwrap();
+    assert_eq!(registry.admin, admin.pubkey());
+    assert_eq!(registry.providers.len(), 1);
+}
+
+#[tokio::test]
+async fn only_the_upgrade_authority_creates_the_liquidity_registry() {
+    let mut env = TestEnv::empty().await;
+    let intruder = intruder(&mut env);
+    let ix = instruction::register_liquidity(intruder.pubkey(), env.usd.liquidity.pubkey());
+    let result = env.process(&[ix], &[&intruder]).await;
+    assert_fx_error(result, FxError::Unauthorized);
+    assert!(liquidity_registry(&mut env).await.is_none());
+}
+
+#[tokio::test]
+async fn spoofed_program_data_is_rejected() {
+    let mut env = TestEnv::empty().await;
+    let intruder = intruder(&mut env);
+    let spoofed = Pubkey::new_unique();
+    env.set_account(&spoofed, program_data(&intruder.pubkey()));
+
+    let mut ix = instruction::register_liquidity(intruder.pubkey(), env.usd.liquidity.pubkey());
+    ix.replace_account(&pda_program_data().0, spoofed);
+    let result = env.process(&[ix], &[&intruder]).await;
+    assert_fx_error(result, FxError::Unauthorized);
+}
+
+#[tokio::test]
+async fn liquidity_providers_can_be_deregistered() {
+    let mut env = TestEnv::start().await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let liquidity = env.usd.liquidity.pubkey();
+    let ix = instruction::deregister_liquidity(admin.pubkey(), liquidity);
+    env.process(std::slice::from_ref(&ix), &[&admin])
+        .await
+        .unwrap();
+
+    let registry = liquidity_registry(&mut env).await.unwrap();
+    assert!(registry
+        .providers
+        .iter()
+        .all(|entry| entry.liquidity != liquidity));
+    let result = env.process(&[ix], &[&admin]).await;
+    assert_fx_error(result, FxError::NoLiquidity);
+}
+
+#[tokio::test]
+async fn only_the_admin_can_register_and_deregister_liquidity() {
+    let mut env = TestEnv::start().await;
+    let intruder = intruder(&mut env);
+    let liquidity = Keypair::new().pubkey();
+    let usd_mint = env.usd.mint;
+    env.set_account(&liquidity, common::token_account(&usd_mint, &liquidity, 0));
+
+    let ix = instruction::register_liquidity(intruder.pubkey(), liquidity);
+    let result = env.process(&[ix], &[&intruder]).await;
+    assert_fx_error(result, FxError::Unauthorized);
+
+    let ix = instruction::deregister_liquidity(intruder.pubkey(), env.usd.liquidity.pubkey());
+    let result = env.process(&[ix], &[&intruder]).await;
+    assert_fx_error(result, FxError::Unauthorized);
+    assert_eq!(
+        liquidity_registry(&mut env).await.unwrap().providers.len(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn upgrade_authority_creates_the_feed_registry() {
+    let mut env = TestEnv::empty().await;
+    let admin = Keypair::from_bytes(&env.admin.to_bytes()).unwrap();
+    let feed = usd_to_eur(&env);
+    let ix = instruction::add_fx_feed(admin.pubkey(), feed);
+    env.process(&[ix], &[&admin]).await.unwrap();
+
+    let registry = feed_registry(&mut env).await.unwrap();
+    assert_eq!(registry.admin, admin.pubkey());
+    assert_eq!(registry.feeds, vec![feed]);
+}
+
+#[tokio::test]
+async fn only_the_upgrade_authority_creates_the_feed_registry() {
+    let mut env = TestEnv::empty().await;
+    let intruder = intruder(&mut env);
+    let ix = instruction::add_fx_feed(intruder.pubkey(), usd_to_eur(&env));
+    let result = env.process(&[ix], &[&intruder]).await;
+    assert_fx_error(result, FxError::Unauthorized);
+    assert!(feed_registry(&mut env).await.is_none());
+}
+
+#[tokio::test]
+async fn only_the_admin_can_change_feeds() {
+    let mut env = TestEnv::with_static_feed().await;
+    let intruder = intruder(&mut env);
+    let feed = FxFeed {
+        feed: Pubkey::new_unique(),
+        ..env.feed()
+    };
+
+    let ix = instruction::update_fx_feed(intruder.pubkey(), feed);
+    let result = env.process(&[ix], &[&intruder]).await;
+    assert_fx_error(result, FxError::Unauthorized);
+
+    let ix = instruction::remove_fx_feed(intruder.pubkey(), feed.from_mint, feed.to_mint);
+    let result = env.process(&[ix], &[&intruder]).await;
+    assert_fx_error(result, FxError::Unauthorized);
+
+    let ix = instruction::set_base_mint(intruder.pubkey(), Some(env.usd.mint));
+    let result = env.process(&[ix], &[&intruder]).await;
+    assert_fx_error(result, FxError::Unauthorized);
+
+    let registry = feed_registry(&mut env).await.unwrap();
+    assert_eq!(registry.feeds, vec![env.feed()]);
+    assert_eq!(registry.base_mint, None);
+}
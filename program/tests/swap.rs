@@ -0,0 +1,387 @@
+mod common;
+
+use common::{assert_fx_error, TestEnv, LIQUIDITY, STATIC_RATE};
+use m10_fx_solana::error::FxError;
+use m10_fx_solana::state::{
+    FxData, FxDataV0, FxFeed, FX_DATA_INITIALIZER_OFFSET, FX_DATA_TO_HOLDING_OFFSET,
+};
+use m10_fx_solana::utils::pda_fx_account;
+use rust_decimal::Decimal;
+use solana_program::instruction::InstructionError;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program::system_instruction;
+use solana_program::system_program;
+use solana_sdk::account::Account;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+use std::str::FromStr;
+use std::time::Duration;
+
+const AMOUNT: u64 = 10_000;
+const VALID_FOR: Duration = Duration::from_secs(300);
+
+fn limits(lower: &str, upper: &str) -> std::ops::Range<Decimal> {
+    Decimal::from_str(lower).unwrap()..Decimal::from_str(upper).unwrap()
+}
+
+#[tokio::test]
+async fn initiate_stores_the_swap() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+
+    let now = env.now().await;
+    env.initiate(&swap, limits("0.8", "1.0"), VALID_FOR)
+        .await
+        .unwrap();
+
+    let fx_data = env.fx_data(&swap.fx_account).await;
+    assert!(fx_data.is_initialized);
+    assert_eq!(fx_data.initializer, swap.initializer.pubkey());
+    assert_eq!(fx_data.to_holding, swap.to_account);
+    assert_eq!(fx_data.from_liquidity, env.usd.liquidity.pubkey());
+    assert_eq!(fx_data.to_liquidity, env.eur.liquidity.pubkey());
+    assert_eq!(fx_data.amount, AMOUNT);
+    assert_eq!(fx_data.limits, limits("0.8", "1.0"));
+    assert!(fx_data.valid_until >= now + VALID_FOR.as_secs() as i64);
+    assert_eq!(fx_data.fx_feed, env.feed().feed);
+
+    // The funds are escrowed in the swap's vault & the holding account is closed
+    assert_eq!(env.balance(&swap.vault()).await, AMOUNT);
+    assert_eq!(env.balance(&env.usd.liquidity.pubkey()).await, LIQUIDITY);
+    assert!(env.account(&swap.holding.pubkey()).await.is_none());
+}
+
+#[tokio::test]
+async fn initiate_twice_is_rejected() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits("0.8", "1.0"), VALID_FOR)
+        .await
+        .unwrap();
+
+    // Reusing the nonce of an open swap
+    env.set_account(
+        &swap.holding.pubkey(),
+        common::token_account(&env.usd.mint, &swap.vault(), AMOUNT),
+    );
+    let result = env.initiate(&swap, limits("0.8", "1.0"), VALID_FOR).await;
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::AccountAlreadyInitialized)
+    );
+}
+
+#[tokio::test]
+async fn fx_accounts_are_derived_from_the_initializer_and_nonce() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits("0.8", "1.0"), VALID_FOR)
+        .await
+        .unwrap();
+    let initializer = Keypair::from_bytes(&swap.initializer.to_bytes()).unwrap();
+    let next = env.swap_with_nonce(initializer, 1, AMOUNT);
+    env.initiate(&next, limits("0.8", "1.0"), VALID_FOR)
+        .await
+        .unwrap();
+
+    let initializer = swap.initializer.pubkey();
+    assert_eq!(swap.fx_account, pda_fx_account(&initializer, 0).0);
+    assert_eq!(next.fx_account, pda_fx_account(&initializer, 1).0);
+    assert_ne!(swap.fx_account, next.fx_account);
+    assert_eq!(env.fx_data(&next.fx_account).await.amount, AMOUNT);
+}
+
+#[tokio::test]
+async fn fx_accounts_can_be_filtered_by_initializer_and_recipient() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits("0.8", "1.0"), VALID_FOR)
+        .await
+        .unwrap();
+
+    let data = env.account(&swap.fx_account).await.unwrap().data;
+    let field = |offset: usize| &data[offset..offset + 32];
+    assert_eq!(
+        field(FX_DATA_INITIALIZER_OFFSET),
+        swap.initializer.pubkey().as_ref()
+    );
+    assert_eq!(field(FX_DATA_TO_HOLDING_OFFSET), swap.to_account.as_ref());
+}
+
+#[tokio::test]
+async fn execute_within_limits_is_deferred() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits("0.8", "1.0"), VALID_FOR)
+        .await
+        .unwrap();
+
+    assert_fx_error(env.execute(&swap).await, FxError::SwapConditionsNotMet);
+    assert_eq!(env.balance(&swap.to_account).await, 0);
+    assert!(env.account(&swap.fx_account).await.is_some());
+}
+
+#[tokio::test]
+async fn execute_after_expiry_settles_at_market_rate() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits("0.8", "1.0"), VALID_FOR)
+        .await
+        .unwrap();
+
+    env.warp(VALID_FOR + Duration::from_secs(1)).await;
+    env.execute(&swap).await.unwrap();
+
+    let expected: u64 = (Decimal::from(AMOUNT) * STATIC_RATE).try_into().unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, expected);
+    assert_eq!(
+        env.balance(&env.eur.liquidity.pubkey()).await,
+        LIQUIDITY - expected
+    );
+    // The escrowed funds are released to the liquidity provider
+    assert_eq!(
+        env.balance(&env.usd.liquidity.pubkey()).await,
+        LIQUIDITY + AMOUNT
+    );
+    assert!(env.account(&swap.vault()).await.is_none());
+    assert!(env.account(&swap.fx_account).await.is_none());
+}
+
+#[tokio::test]
+async fn execute_outside_limits_settles_at_market_rate() {
+    let mut env = TestEnv::with_static_feed().await;
+    let below = env.swap(AMOUNT);
+    env.initiate(&below, limits("0.5", "0.8"), VALID_FOR)
+        .await
+        .unwrap();
+    let above = env.swap(AMOUNT);
+    env.initiate(&above, limits("1.0", "1.2"), VALID_FOR)
+        .await
+        .unwrap();
+
+    env.execute(&below).await.unwrap();
+    env.execute(&above).await.unwrap();
+
+    assert_eq!(env.balance(&below.to_account).await, 9_000);
+    assert_eq!(env.balance(&above.to_account).await, 9_000);
+}
+
+#[tokio::test]
+async fn execute_with_pyth_feed_applies_the_exponent() {
+    let mut env = TestEnv::with_pyth_feed(11_000, -4).await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits("0.8", "1.0"), VALID_FOR)
+        .await
+        .unwrap();
+
+    env.execute(&swap).await.unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, 11_000);
+}
+
+#[tokio::test]
+async fn initiate_creates_pre_funded_accounts() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+
+    // Anyone can send lamports to the predictable addresses of a swap, below or above rent exemption
+    let system = system_program::id();
+    env.set_account(&swap.fx_account, Account::new(1, 0, &system));
+    env.set_account(&swap.vault(), Account::new(1_000_000_000, 0, &system));
+
+    env.initiate(&swap, limits("0.5", "0.8"), VALID_FOR)
+        .await
+        .unwrap();
+    let fx_account = env.account(&swap.fx_account).await.unwrap();
+    assert_eq!(fx_account.owner, m10_fx_solana::id());
+    assert!(fx_account.lamports >= Rent::default().minimum_balance(FxData::LEN));
+    assert_eq!(env.balance(&swap.vault()).await, AMOUNT);
+
+    env.execute(&swap).await.unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, 9_000);
+}
+
+#[tokio::test]
+async fn closed_fx_accounts_cannot_be_revived() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits("0.5", "0.8"), VALID_FOR)
+        .await
+        .unwrap();
+
+    // Refund the rent of the closed FX account within the same transaction
+    let liquidity = Keypair::from_bytes(&env.eur.liquidity.to_bytes()).unwrap();
+    let execute_ix = env.execute_ix(&swap, &liquidity.pubkey(), None);
+    let revive_ix = system_instruction::transfer(
+        &env.context.payer.pubkey(),
+        &swap.fx_account,
+        Rent::default().minimum_balance(FxData::LEN),
+    );
+    let result = env
+        .process(&[execute_ix.clone(), revive_ix, execute_ix], &[&liquidity])
+        .await;
+    assert_fx_error(result, FxError::InvalidFxAccount);
+
+    env.execute(&swap).await.unwrap();
+    assert!(env.account(&swap.fx_account).await.is_none());
+}
+
+#[tokio::test]
+async fn closed_fx_accounts_reject_instructions_in_the_same_transaction() {
+    let mut env = TestEnv::with_static_feed().await;
+    let executed = env.swap(AMOUNT);
+    env.initiate(&executed, limits("0.5", "0.8"), VALID_FOR)
+        .await
+        .unwrap();
+    let cancelled = env.swap(AMOUNT);
+    env.initiate(&cancelled, limits("0.8", "1.0"), VALID_FOR)
+        .await
+        .unwrap();
+
+    let liquidity = Keypair::from_bytes(&env.eur.liquidity.to_bytes()).unwrap();
+    let execute_ix = env.execute_ix(&executed, &liquidity.pubkey(), None);
+    let result = env
+        .process(&[execute_ix.clone(), execute_ix], &[&liquidity])
+        .await;
+    assert_fx_error(result, FxError::InvalidFxAccount);
+
+    let initializer = Keypair::from_bytes(&cancelled.initializer.to_bytes()).unwrap();
+    let cancel_ix = env.cancel_ix(&cancelled);
+    let result = env
+        .process(&[cancel_ix.clone(), cancel_ix], &[&initializer])
+        .await;
+    assert_fx_error(result, FxError::InvalidFxAccount);
+
+    // Neither swap was settled twice
+    assert_eq!(env.balance(&executed.to_account).await, 0);
+    assert_eq!(env.balance(&cancelled.refund_account).await, 0);
+    assert!(env.account(&executed.fx_account).await.is_some());
+    assert!(env.account(&cancelled.fx_account).await.is_some());
+}
+
+#[tokio::test]
+async fn cancel_refunds_the_initializer() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits("0.8", "1.0"), VALID_FOR)
+        .await
+        .unwrap();
+
+    env.cancel(&swap).await.unwrap();
+
+    assert_eq!(env.balance(&swap.refund_account).await, AMOUNT);
+    assert_eq!(env.balance(&env.usd.liquidity.pubkey()).await, LIQUIDITY);
+    assert!(env.account(&swap.fx_account).await.is_none());
+}
+
+/// The [`swap`]'s FX account in the layout of the original program, which paid the amount to the USD provider
+fn baseline_fx_account(env: &TestEnv, swap: &common::Swap, valid_until: i64) -> Account {
+    let limits = limits("0.5", "0.8");
+    let mut account = Account::new(
+        Rent::default().minimum_balance(FxDataV0::LEN),
+        FxDataV0::LEN,
+        &m10_fx_solana::id(),
+    );
+    let mut data = vec![1];
+    data.extend_from_slice(swap.initializer.pubkey().as_ref());
+    data.extend_from_slice(swap.holding.pubkey().as_ref());
+    data.extend_from_slice(swap.to_account.as_ref());
+    data.extend_from_slice(env.usd.liquidity.pubkey().as_ref());
+    data.extend_from_slice(env.eur.liquidity.pubkey().as_ref());
+    data.extend_from_slice(&swap.amount.to_be_bytes());
+    data.extend_from_slice(&limits.end.serialize());
+    data.extend_from_slice(&limits.start.serialize());
+    data.extend_from_slice(&valid_until.to_be_bytes());
+    data.extend_from_slice(env.feed().feed.as_ref());
+    account.data = data;
+    account
+}
+
+#[tokio::test]
+async fn migrate_upgrades_unversioned_accounts() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    let valid_until = env.now().await + VALID_FOR.as_secs() as i64;
+    let account = baseline_fx_account(&env, &swap, valid_until);
+    env.set_account(&swap.fx_account, account);
+    let usd_liquidity = env.usd.liquidity.pubkey();
+    let usd_mint = env.usd.mint;
+    env.set_account(
+        &usd_liquidity,
+        common::token_account(&usd_mint, &usd_liquidity, LIQUIDITY + AMOUNT),
+    );
+    assert_fx_error(env.execute(&swap).await, FxError::MigrationRequired);
+
+    let payer = env.context.payer.pubkey();
+    let ix = m10_fx_solana::instruction::migrate(payer, swap.fx_account, usd_liquidity, usd_mint);
+    let liquidity = Keypair::from_bytes(&env.usd.liquidity.to_bytes()).unwrap();
+    env.process(&[ix], &[&liquidity]).await.unwrap();
+
+    let account = env.account(&swap.fx_account).await.unwrap();
+    assert_eq!(account.data.len(), FxData::LEN);
+    assert!(Rent::default().is_exempt(account.lamports, FxData::LEN));
+    let fx_data = FxData::unpack(&account.data).unwrap();
+    assert_eq!(fx_data.initializer, swap.initializer.pubkey());
+    assert_eq!(fx_data.to_holding, swap.to_account);
+    assert_eq!(fx_data.amount, AMOUNT);
+    assert_eq!(fx_data.limits, limits("0.5", "0.8"));
+    assert_eq!(fx_data.valid_until, valid_until);
+    assert_eq!(env.balance(&swap.vault()).await, AMOUNT);
+    assert_eq!(env.balance(&usd_liquidity).await, LIQUIDITY);
+
+    // The original swaps quoted the demo rates without an oracle program
+    env.feed = Some(FxFeed {
+        oracle_program: Pubkey::default(),
+        ..env.feed()
+    });
+    env.execute(&swap).await.unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, 9_000);
+    assert_eq!(env.balance(&usd_liquidity).await, LIQUIDITY + AMOUNT);
+}
+
+#[tokio::test]
+async fn partial_fills_settle_the_swap_in_several_executions() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits("0.5", "0.8"), VALID_FOR)
+        .await
+        .unwrap();
+
+    // The default provider fills part of the swap
+    let liquidity = Keypair::from_bytes(&env.eur.liquidity.to_bytes()).unwrap();
+    env.fill(&swap, &liquidity, 4_000).await.unwrap();
+    let fx_data = env.fx_data(&swap.fx_account).await;
+    assert_eq!(fx_data.amount_filled, 4_000);
+    assert_eq!(fx_data.amount_remaining(), 6_000);
+    assert_eq!(env.balance(&swap.to_account).await, 3_600);
+    assert_eq!(env.balance(&swap.vault()).await, 6_000);
+    assert_eq!(
+        env.balance(&env.usd.liquidity.pubkey()).await,
+        LIQUIDITY + 4_000
+    );
+
+    // Filling the remainder closes the swap
+    env.fill(&swap, &liquidity, AMOUNT).await.unwrap();
+    assert_eq!(env.balance(&swap.to_account).await, 9_000);
+    assert_eq!(env.balance(&liquidity.pubkey()).await, LIQUIDITY - 9_000);
+    assert!(env.account(&swap.fx_account).await.is_none());
+}
+
+#[tokio::test]
+async fn cancel_refunds_the_unfilled_amount() {
+    let mut env = TestEnv::with_static_feed().await;
+    let swap = env.swap(AMOUNT);
+    env.initiate(&swap, limits("0.5", "0.8"), VALID_FOR)
+        .await
+        .unwrap();
+
+    let liquidity = Keypair::from_bytes(&env.eur.liquidity.to_bytes()).unwrap();
+    env.fill(&swap, &liquidity, 2_500).await.unwrap();
+    env.cancel(&swap).await.unwrap();
+
+    assert_eq!(env.balance(&swap.to_account).await, 2_250);
+    assert_eq!(env.balance(&swap.refund_account).await, 7_500);
+    assert!(env.account(&swap.vault()).await.is_none());
+    assert!(env.account(&swap.fx_account).await.is_none());
+}